@@ -0,0 +1,342 @@
+//! Keep the size trees of local files on disk, so that they can be rendered
+//! again without being parsed.
+use crate::input::{self, InputFormat, Kind, ParseSettings};
+use crate::{Node, Tag};
+use anyhow::*;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+/// The header of cache files; the format may change between versions.
+const MAGIC: &[u8] = concat!("toison ", env!("CARGO_PKG_VERSION"), "\n").as_bytes();
+
+/// The kinds of nodes, by their index in cache files.
+const KINDS: [Kind; 8] = [
+    Kind::Object,
+    Kind::Array,
+    Kind::String,
+    Kind::Number,
+    Kind::Bool,
+    Kind::Null,
+    Kind::Binary,
+    Kind::Other,
+];
+
+/// The cache file of the size tree of `file` parsed as `format` with
+/// `settings`, if `file` is a local file; it changes with the file, and with
+/// any setting altering the size tree. Its name is made of the hashes of the
+/// path of the file, of its version, and of the settings, in that order.
+pub fn path(file: &str, format: InputFormat, settings: &ParseSettings) -> Option<PathBuf> {
+    if input::is_url(file) {
+        return None;
+    }
+    let metadata = std::fs::metadata(file).ok().filter(|m| m.is_file())?;
+    let dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".cache")))?;
+
+    fn hash(x: impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+    let path = hash(std::fs::canonicalize(file).ok()?);
+    let version = hash((metadata.len(), metadata.modified().ok()?));
+    let patterns = |rs: &[Regex]| rs.iter().map(Regex::as_str).collect::<Vec<_>>().join("\n");
+    let settings = hash(format!(
+        "{:?}",
+        (
+            format,
+            (settings.relaxed, settings.uncompressed),
+            (&settings.proto_descriptor, &settings.message_type),
+            (settings.compressed, settings.tokenizer, settings.distinct),
+            (&settings.root, &settings.filter, &settings.select),
+            (
+                settings.expand_arrays,
+                settings.element_schema,
+                settings.sample
+            ),
+            &settings.id_field,
+            (
+                patterns(&settings.include_keys),
+                patterns(&settings.exclude_keys)
+            ),
+            (settings.preview, settings.grep.as_ref().map(Regex::as_str)),
+            (settings.max_depth, settings.min_size),
+            settings.max_parse_depth,
+        )
+    ));
+    Some(
+        dir.join("toison")
+            .join(format!("{:016x}-{:016x}-{:016x}", path, version, settings)),
+    )
+}
+
+/// Store the size tree `root` in the cache file `path`, and remove the cache
+/// files of the other versions of the same file, which can not be used
+/// anymore.
+pub fn store(path: &Path, root: &Node) -> Result<()> {
+    write(path, MAGIC, root)?;
+    let name = path.file_name().unwrap().to_string_lossy();
+    let (Some((current, _)), Some((file, _))) = (name.rsplit_once('-'), name.split_once('-'))
+    else {
+        return Ok(());
+    };
+    let (current, file) = (format!("{}-", current), format!("{}-", file));
+    for entry in std::fs::read_dir(path.parent().unwrap())? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // Files being written by other runs have an extension
+        if name.starts_with(&file) && !name.starts_with(&current) && !name.contains('.') {
+            // Possibly removed by another run meanwhile
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Write the size tree `root` to the file `path`, after `header`; should the
+/// encoding of the nodes change, so must the headers of the files storing
+/// them.
+pub(crate) fn write(path: &Path, header: &[u8], root: &Node) -> Result<()> {
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    // Written aside first, so that other runs never read half-written trees
+    let partial = path.with_extension(format!("{}", std::process::id()));
+    let mut out = BufWriter::new(File::create(&partial)?);
+    out.write_all(header)?;
+
+    // Every tag is written once, then referred to by its index
+    let mut tags = HashMap::<&str, usize>::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        match node.tag.as_deref() {
+            None => varint(&mut out, 0)?,
+            Some(tag) => {
+                if let Some(&i) = tags.get(tag) {
+                    varint(&mut out, i + 1)?;
+                } else {
+                    tags.insert(tag, tags.len());
+                    varint(&mut out, tags.len())?;
+                    string(&mut out, tag)?;
+                }
+            }
+        }
+        out.write_all(&[KINDS.iter().position(|k| *k == node.kind).unwrap() as u8])?;
+        for size in [
+            node.len,
+            node.size_b,
+            node.size_c,
+            node.size_s,
+            node.size_m,
+            node.size_l,
+            node.size_u,
+            node.size_z,
+            node.size_t,
+            node.size_w,
+            node.size_d,
+            node.key_size,
+            node.depth,
+        ] {
+            varint(&mut out, size)?;
+        }
+        out.write_all(&[node.matched as u8])?;
+        match &node.preview {
+            None => varint(&mut out, 0)?,
+            Some(preview) => {
+                varint(&mut out, 1)?;
+                string(&mut out, preview)?;
+            }
+        }
+        match &node.values {
+            None => varint(&mut out, 0)?,
+            Some(values) => {
+                varint(&mut out, values.len() + 1)?;
+                for value in values.iter() {
+                    out.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+        match &node.children {
+            None => varint(&mut out, 0)?,
+            Some(children) => {
+                varint(&mut out, children.len() + 1)?;
+                stack.extend(children.iter().rev());
+            }
+        }
+    }
+    out.flush()?;
+    drop(out);
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Load the size tree stored in the cache file `path`, without the nodes
+/// pruned by `settings`.
+pub fn load(path: &Path, settings: &ParseSettings) -> Result<Node> {
+    read(path, MAGIC, settings)
+}
+
+/// Read the size tree written to the file `path` after `header`, without
+/// the nodes pruned by `settings`.
+pub(crate) fn read(path: &Path, header: &[u8], settings: &ParseSettings) -> Result<Node> {
+    let content = std::fs::read(path)?;
+    let mut r = Reader {
+        content: content
+            .strip_prefix(header)
+            .ok_or_else(|| anyhow!("not written by this version"))?,
+        tags: Vec::new(),
+    };
+
+    // The containers being read, with their children read so far and the
+    // number of those left to read
+    let mut stack: Vec<(Node, Vec<Node>, usize)> = Vec::new();
+    loop {
+        let (mut node, children) = r.node()?;
+        if stack.is_empty() {
+            // The whole tree is weighed first
+            if let Some((unit, _)) = settings.threshold {
+                settings.weighed.store(node.size(unit), Ordering::Relaxed);
+            }
+        }
+        match children {
+            None => {}
+            Some(0) => node.children = Some(Vec::new()),
+            Some(n) if node.prunable(stack.len(), settings) => {
+                node.children = Some(Vec::new());
+                r.skip(n)?;
+            }
+            Some(n) => {
+                stack.push((node, Vec::with_capacity(n.min(1 << 16)), n));
+                continue;
+            }
+        }
+
+        // Hand the node over to its parent, and the latter to its own parent
+        // once complete
+        loop {
+            let Some((_, children, left)) = stack.last_mut() else {
+                ensure!(r.content.is_empty(), "trailing data");
+                return Ok(node);
+            };
+            children.push(node);
+            *left -= 1;
+            if *left > 0 {
+                break;
+            }
+            let (mut parent, children, _) = stack.pop().unwrap();
+            parent.children = Some(children);
+            node = parent;
+        }
+    }
+}
+
+fn varint<W: Write>(out: &mut W, mut x: usize) -> Result<()> {
+    while x >= 0x80 {
+        out.write_all(&[(x as u8) | 0x80])?;
+        x >>= 7;
+    }
+    out.write_all(&[x as u8])?;
+    Ok(())
+}
+
+fn string<W: Write>(out: &mut W, s: &str) -> Result<()> {
+    varint(out, s.len())?;
+    out.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+/// A cache file being read.
+struct Reader<'a> {
+    /// What is left to read
+    content: &'a [u8],
+    /// The tags read so far, by index
+    tags: Vec<Tag>,
+}
+impl Reader<'_> {
+    fn bytes(&mut self, n: usize) -> Result<&[u8]> {
+        ensure!(n <= self.content.len(), "truncated cache file");
+        let (head, tail) = self.content.split_at(n);
+        self.content = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn varint(&mut self) -> Result<usize> {
+        let mut x = 0;
+        for shift in (0..usize::BITS).step_by(7) {
+            let b = self.byte()?;
+            x |= ((b & 0x7f) as usize) << shift;
+            if b < 0x80 {
+                return Ok(x);
+            }
+        }
+        bail!("malformed integer")
+    }
+
+    fn string(&mut self) -> Result<&str> {
+        let len = self.varint()?;
+        Ok(std::str::from_utf8(self.bytes(len)?)?)
+    }
+
+    /// Read a node without its children, and their number if it has any.
+    fn node(&mut self) -> Result<(Node, Option<usize>)> {
+        let tag = match self.varint()? {
+            0 => None,
+            i if i == self.tags.len() + 1 => {
+                let tag = Tag::from(self.string()?);
+                self.tags.push(tag.clone());
+                Some(tag)
+            }
+            i => Some(self.tags.get(i - 1).context("unknown tag")?.clone()),
+        };
+        // The fields are read in the order they were written
+        let node = Node {
+            tag,
+            kind: *KINDS.get(self.byte()? as usize).context("unknown kind")?,
+            len: self.varint()?,
+            size_b: self.varint()?,
+            size_c: self.varint()?,
+            size_s: self.varint()?,
+            size_m: self.varint()?,
+            size_l: self.varint()?,
+            size_u: self.varint()?,
+            size_z: self.varint()?,
+            size_t: self.varint()?,
+            size_w: self.varint()?,
+            size_d: self.varint()?,
+            key_size: self.varint()?,
+            depth: self.varint()?,
+            matched: self.byte()? != 0,
+            preview: match self.varint()? {
+                0 => None,
+                _ => Some(self.string()?.into()),
+            },
+            values: match self.varint()? {
+                0 => None,
+                n => Some(Box::new((1..n).map(|_| self.u64()).collect::<Result<_>>()?)),
+            },
+            children: None,
+        };
+        Ok((node, self.varint()?.checked_sub(1)))
+    }
+
+    /// Read past `n` sibling nodes and their descendants.
+    fn skip(&mut self, mut n: usize) -> Result<()> {
+        while n > 0 {
+            n = n - 1 + self.node()?.1.unwrap_or(0);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+//! Arithmetic expressions over the units of a node, used as custom weights.
+use crate::{Node, Unit};
+use anyhow::*;
+use clap::ValueEnum;
+use std::str::FromStr;
+
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Number(f64),
+    Unit(Unit),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+}
+impl Expr {
+    pub fn eval(&self, node: &Node) -> f64 {
+        match self {
+            Expr::Number(x) => *x,
+            Expr::Unit(u) => node.size(*u) as f64,
+            Expr::Neg(e) => -e.eval(node),
+            Expr::Binary(l, op, r) => {
+                let (l, r) = (l.eval(node), r.eval(node));
+                match op {
+                    '+' => l + r,
+                    '-' => l - r,
+                    '*' => l * r,
+                    '/' if r == 0. => 0.,
+                    '/' => l / r,
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    /// Whether the expression depends on `unit`.
+    pub fn uses(&self, unit: Unit) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Unit(u) => *u == unit,
+            Expr::Neg(e) => e.uses(unit),
+            Expr::Binary(l, _, r) => l.uses(unit) || r.uses(unit),
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Expr> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            i: 0,
+        };
+        let expr = parser.sum()?;
+        ensure!(
+            parser.i == parser.tokens.len(),
+            "unexpected `{}`",
+            parser.tokens[parser.i]
+        );
+        Ok(expr)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+}
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(x) => write!(f, "{}", x),
+            Token::Ident(s) => write!(f, "{}", s),
+            Token::Op(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Number(
+                number
+                    .parse()
+                    .with_context(|| format!("invalid number `{}`", number))?,
+            ));
+        } else if c.is_ascii_alphabetic() {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                ident.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Ident(ident));
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else {
+            bail!("unexpected `{}`", c);
+        }
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser for the usual arithmetic grammar.
+struct Parser {
+    tokens: Vec<Token>,
+    i: usize,
+}
+impl Parser {
+    fn next(&mut self) -> Option<&Token> {
+        self.i += 1;
+        self.tokens.get(self.i - 1)
+    }
+
+    fn peek_op(&self) -> Option<char> {
+        match self.tokens.get(self.i) {
+            Some(Token::Op(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        let mut lhs = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek_op() {
+            self.i += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        let mut lhs = self.factor()?;
+        while let Some(op @ ('*' | '/')) = self.peek_op() {
+            self.i += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.factor()?));
+        }
+        Ok(lhs)
+    }
+
+    fn factor(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(x)) => Ok(Expr::Number(*x)),
+            Some(Token::Ident(name)) => {
+                let unit =
+                    Unit::from_str(name, true).map_err(|_| anyhow!("unknown unit `{}`", name))?;
+                ensure!(
+                    unit != Unit::Weight,
+                    "a weight expression can not refer to itself"
+                );
+                Ok(Expr::Unit(unit))
+            }
+            Some(Token::Op('-')) => Ok(Expr::Neg(Box::new(self.factor()?))),
+            Some(Token::Op('(')) => {
+                let e = self.sum()?;
+                ensure!(self.next() == Some(&Token::Op(')')), "missing `)`");
+                Ok(e)
+            }
+            Some(t) => bail!("unexpected `{}`", t),
+            None => bail!("unexpected end of expression"),
+        }
+    }
+}
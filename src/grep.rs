@@ -0,0 +1,44 @@
+//! Locate the keys and values matching a pattern in the size tree.
+use crate::{output, DisplaySettings, Node};
+use anyhow::*;
+use regex::Regex;
+use std::io::Write;
+
+/// Write the path, depth and size of every node whose key matches `pattern`,
+/// or whose value was found to match it while parsing.
+pub fn run(
+    root: &Node,
+    pattern: &Regex,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut found = 0;
+    // The nodes still to search, with their path and depth; the next one last
+    let mut stack = vec![(root, String::new(), 0)];
+    while let Some((node, path, depth)) = stack.pop() {
+        // The root tag is not a key of the document
+        let key = depth > 0 && pattern.is_match(node.tag.as_deref().unwrap_or_default());
+        if key || node.matched {
+            writeln!(
+                out,
+                "{:>11} {:>4} {:<5} {}",
+                format!(
+                    "({})",
+                    settings
+                        .counter
+                        .format(node.size(settings.counter), settings.exact)
+                ),
+                format!("d{}", depth),
+                if key { "key" } else { "value" },
+                if path.is_empty() { "/" } else { &path }
+            )?;
+            found += 1;
+        }
+        for child in node.children.iter().flatten().rev() {
+            let path = output::child_path(&path, child.tag.as_deref().unwrap_or_default());
+            stack.push((child, path, depth + 1));
+        }
+    }
+    ensure!(found > 0, "no match for `{}`", pattern);
+    Ok(())
+}
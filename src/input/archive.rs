@@ -0,0 +1,43 @@
+use super::Content;
+use anyhow::*;
+use std::io::{Cursor, Read};
+
+/// Whether a file is a zip or (possibly compressed) tar archive.
+pub fn is_archive(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    matches!(super::extension(path).as_deref(), Some("zip" | "tar")) || lower.ends_with(".tgz")
+}
+
+/// Extract the files of an archive whose name satisfy `filter`.
+pub fn members(
+    path: &str,
+    content: Content,
+    filter: impl Fn(&str) -> bool,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut r = Vec::new();
+    if matches!(super::extension(path).as_deref(), Some("zip")) {
+        let mut archive = zip::ZipArchive::new(Cursor::new(&*content))?;
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let name = file.name()?.into_owned();
+            if file.is_file() && filter(&name) {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content)?;
+                r.push((name, content));
+            }
+        }
+    } else {
+        let content = super::decompress(content)?;
+        let mut archive = tar::Archive::new(&*content);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if entry.header().entry_type().is_file() && filter(&name) {
+                let mut content = Vec::new();
+                entry.read_to_end(&mut content)?;
+                r.push((name, content));
+            }
+        }
+    }
+    Ok(r)
+}
@@ -0,0 +1,117 @@
+use super::{Document, Kind, Shape};
+use anyhow::*;
+use apache_avro::types::Value;
+use std::borrow::Cow;
+
+/// Read all the records of an Avro object container file, decoded according
+/// to the schema embedded in its header.
+pub fn parse(content: &[u8]) -> Result<Value> {
+    apache_avro::Reader::new(content)?
+        .map(|r| r.map_err(Error::from))
+        .collect::<Result<Vec<_>>>()
+        .map(Value::Array)
+}
+
+/// The size of a zig-zag encoded variable-length integer.
+fn varint(n: i64) -> usize {
+    let z = ((n << 1) ^ (n >> 63)) as u64;
+    (64 - z.leading_zeros() as usize).max(1).div_ceil(7)
+}
+
+impl Document for Value {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            Value::Array(xs) => Shape::Array(xs.iter().map(|x| (0, x)).collect()),
+            // Map keys are stored as strings, while record fields are only
+            // described in the schema
+            Value::Map(kvs) => Shape::Object(
+                kvs.iter()
+                    .map(|(k, v)| {
+                        (
+                            Cow::Borrowed(k.as_str()),
+                            varint(k.len() as i64) + k.len(),
+                            v,
+                        )
+                    })
+                    .collect(),
+            ),
+            Value::Record(kvs) => Shape::Object(
+                kvs.iter()
+                    .map(|(k, v)| (Cow::Borrowed(k.as_str()), 0, v))
+                    .collect(),
+            ),
+            Value::Union(_, v) => v.shape(),
+            _ => Shape::Scalar,
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Value::Null => Kind::Null,
+            Value::Boolean(_) => Kind::Bool,
+            Value::Int(_) | Value::Long(_) | Value::Float(_) | Value::Double(_) => Kind::Number,
+            Value::String(_) | Value::Enum(..) => Kind::String,
+            Value::Bytes(_) | Value::Fixed(..) => Kind::Binary,
+            Value::Array(_) => Kind::Array,
+            Value::Map(_) | Value::Record(_) => Kind::Object,
+            Value::Union(_, v) => v.kind(),
+            _ => Kind::Other,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Value::Null => 0,
+            Value::Boolean(_) => 1,
+            Value::Int(x) | Value::Date(x) | Value::TimeMillis(x) => varint(*x as i64),
+            Value::Long(x)
+            | Value::TimeMicros(x)
+            | Value::TimestampMillis(x)
+            | Value::TimestampMicros(x)
+            | Value::TimestampNanos(x)
+            | Value::LocalTimestampMillis(x)
+            | Value::LocalTimestampMicros(x)
+            | Value::LocalTimestampNanos(x) => varint(*x),
+            Value::Float(_) => 4,
+            Value::Double(_) => 8,
+            Value::Bytes(b) => varint(b.len() as i64) + b.len(),
+            Value::String(s) => varint(s.len() as i64) + s.len(),
+            Value::Fixed(n, _) => *n,
+            Value::Enum(i, _) => varint(*i as i64),
+            Value::Union(i, v) => varint(*i as i64) + v.size(),
+            // Decimals may also be stored as fixed, but we can not know it
+            // without the schema
+            Value::Decimal(d) => {
+                let n = Vec::<u8>::try_from(d).map(|b| b.len()).unwrap_or_default();
+                varint(n as i64) + n
+            }
+            // Big decimals are stored as bytes, holding their big-endian
+            // unscaled value followed by their exponent
+            Value::BigDecimal(d) => {
+                let (n, exponent) = d.as_bigint_and_exponent();
+                let n = n.to_signed_bytes_be().len();
+                let inner = varint(n as i64) + n + varint(exponent);
+                varint(inner as i64) + inner
+            }
+            Value::Duration(_) => 12,
+            // UUIDs are stored as strings by default
+            Value::Uuid(_) => 1 + 36,
+            Value::Array(_) | Value::Map(_) | Value::Record(_) => 0,
+        }
+    }
+
+    fn identity(&self) -> Option<Vec<u8>> {
+        Some(format!("{:?}", self).into_bytes())
+    }
+
+    fn label(&self) -> Option<String> {
+        match self {
+            Value::String(s) | Value::Enum(_, s) => Some(s.clone()),
+            Value::Int(i) => Some(i.to_string()),
+            Value::Long(i) => Some(i.to_string()),
+            Value::Uuid(u) => Some(u.to_string()),
+            Value::Union(_, v) => v.label(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use super::{Counter, Document, Kind, ParseSettings, Shape};
+use crate::Node;
+use anyhow::*;
+use bson::{spec::BinarySubtype, Bson};
+use std::borrow::Cow;
+
+/// Parse a BSON file; files containing several documents back-to-back, such
+/// as produced by `mongodump`, are read as an array of documents.
+pub fn parse(content: &[u8]) -> Result<Bson> {
+    let mut documents = Vec::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        ensure!(rest.len() >= 4, "truncated BSON document");
+        let len = i32::from_le_bytes(rest[..4].try_into().unwrap());
+        ensure!(
+            len >= 5 && len as usize <= rest.len(),
+            "invalid BSON document length: {}",
+            len
+        );
+        let (document, tail) = rest.split_at(len as usize);
+        documents.push(Bson::Document(bson::Document::from_reader(document)?));
+        rest = tail;
+    }
+
+    Ok(if documents.len() == 1 {
+        documents.pop().unwrap()
+    } else {
+        Bson::Array(documents)
+    })
+}
+
+/// Build the size tree of a BSON file.
+pub fn load(content: &[u8], settings: &ParseSettings, tag: String) -> Result<Node> {
+    let document = parse(content)?;
+    let mut root = super::build(&document, settings, tag)?;
+    // The documents of a dump are read as an array, whose length, keys and
+    // terminator are not part of the file
+    if let Bson::Array(documents) = &document {
+        if settings.root.is_none() && settings.select.is_none() {
+            let framing = document.overhead()
+                + (0..documents.len())
+                    .map(|i| element_header(&i.to_string()))
+                    .sum::<usize>();
+            root.size_s -= framing;
+            if settings.compressed {
+                root.size_z = root.size_z.saturating_sub(framing);
+            }
+        }
+    }
+    Ok(root)
+}
+
+/// The size of an element header, i.e. its type byte and its NUL-terminated
+/// name.
+fn element_header(key: &str) -> usize {
+    1 + key.len() + 1
+}
+
+impl Document for Bson {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            // BSON arrays are documents keyed by the elements indices
+            Bson::Array(xs) => Shape::Array(
+                xs.iter()
+                    .enumerate()
+                    .map(|(i, x)| (element_header(&i.to_string()), x))
+                    .collect(),
+            ),
+            Bson::Document(kvs) => Shape::Object(
+                kvs.iter()
+                    .map(|(k, v)| (Cow::Borrowed(k.as_str()), element_header(k), v))
+                    .collect(),
+            ),
+            _ => Shape::Scalar,
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Bson::Null | Bson::Undefined => Kind::Null,
+            Bson::Boolean(_) => Kind::Bool,
+            Bson::Double(_) | Bson::Int32(_) | Bson::Int64(_) | Bson::Decimal128(_) => Kind::Number,
+            Bson::String(_) | Bson::Symbol(_) => Kind::String,
+            Bson::Binary(_) => Kind::Binary,
+            Bson::Array(_) => Kind::Array,
+            Bson::Document(_) => Kind::Object,
+            _ => Kind::Other,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Bson::String(s) | Bson::JavaScriptCode(s) | Bson::Symbol(s) => 4 + s.len() + 1,
+            Bson::Binary(b) => {
+                4 + 1
+                    + b.bytes.len()
+                    + if b.subtype == BinarySubtype::BinaryOld {
+                        4
+                    } else {
+                        0
+                    }
+            }
+            Bson::Array(_) | Bson::Document(_) => 0,
+            v => {
+                // Wrap the value in a single-element document with an empty
+                // key, then remove the document overhead.
+                let mut counter = Counter(0);
+                bson::doc! { "": v.clone() }
+                    .to_writer(&mut counter)
+                    .expect("writing to a counter can not fail");
+                counter.0 - 4 - element_header("") - 1
+            }
+        }
+    }
+
+    fn overhead(&self) -> usize {
+        match self {
+            // The length, and the terminating NUL
+            Bson::Array(_) | Bson::Document(_) => 4 + 1,
+            _ => 0,
+        }
+    }
+
+    fn chars(&self) -> usize {
+        match self {
+            Bson::String(s) | Bson::JavaScriptCode(s) | Bson::Symbol(s) => s.chars().count(),
+            v => v.size(),
+        }
+    }
+
+    fn identity(&self) -> Option<Vec<u8>> {
+        Some(format!("{:?}", self).into_bytes())
+    }
+
+    fn label(&self) -> Option<String> {
+        match self {
+            Bson::String(s) => Some(s.clone()),
+            Bson::Int32(i) => Some(i.to_string()),
+            Bson::Int64(i) => Some(i.to_string()),
+            Bson::ObjectId(id) => Some(id.to_hex()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use super::{Counter, Document, Kind, Shape};
+use anyhow::*;
+use ciborium::Value;
+use std::borrow::Cow;
+
+pub fn parse(content: &[u8]) -> Result<Value> {
+    ciborium::from_reader(content).map_err(|e| anyhow!("{}", e))
+}
+
+/// The size of a value once re-encoded; as encoders use the most compact
+/// representation available, this should match the original document.
+fn encoded_size(v: &Value) -> usize {
+    let mut counter = Counter(0);
+    ciborium::into_writer(v, &mut counter).expect("writing to a counter can not fail");
+    counter.0
+}
+
+/// The size of the header of a data item whose argument, e.g. its length or
+/// its tag number, is `n`.
+fn header(n: u64) -> usize {
+    match n {
+        0..24 => 1,
+        24..256 => 2,
+        256..65536 => 3,
+        65536..0x1_0000_0000 => 5,
+        _ => 9,
+    }
+}
+
+fn key(k: &Value) -> Cow<'_, str> {
+    match k {
+        Value::Text(s) => Cow::Borrowed(s),
+        Value::Integer(i) => Cow::Owned(i128::from(*i).to_string()),
+        Value::Float(f) => Cow::Owned(f.to_string()),
+        Value::Bool(b) => Cow::Owned(b.to_string()),
+        Value::Null => Cow::Borrowed("null"),
+        Value::Tag(_, k) => key(k),
+        k => Cow::Owned(format!("{:?}", k)),
+    }
+}
+
+impl Document for Value {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            Value::Array(xs) => Shape::Array(xs.iter().map(|x| (0, x)).collect()),
+            Value::Map(kvs) => Shape::Object(
+                kvs.iter()
+                    .map(|(k, v)| (key(k), encoded_size(k), v))
+                    .collect(),
+            ),
+            // Tags are transparent, e.g. COSE structures are tagged arrays
+            Value::Tag(_, v) => v.shape(),
+            _ => Shape::Scalar,
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Integer(_) | Value::Float(_) => Kind::Number,
+            Value::Text(_) => Kind::String,
+            Value::Bytes(_) => Kind::Binary,
+            Value::Array(_) => Kind::Array,
+            Value::Map(_) => Kind::Object,
+            Value::Tag(_, v) => v.kind(),
+            _ => Kind::Other,
+        }
+    }
+
+    fn size(&self) -> usize {
+        encoded_size(self)
+    }
+
+    fn overhead(&self) -> usize {
+        match self {
+            Value::Array(xs) => header(xs.len() as u64),
+            Value::Map(kvs) => header(kvs.len() as u64),
+            // The tags of scalars are part of their size
+            Value::Tag(tag, v) if !matches!(v.shape(), Shape::Scalar) => {
+                header(*tag) + v.overhead()
+            }
+            _ => 0,
+        }
+    }
+
+    fn chars(&self) -> usize {
+        match self {
+            Value::Text(s) => s.chars().count(),
+            v => v.size(),
+        }
+    }
+
+    fn serialize(&self) -> Option<Vec<u8>> {
+        let mut content = Vec::new();
+        ciborium::into_writer(self, &mut content).ok()?;
+        Some(content)
+    }
+
+    fn label(&self) -> Option<String> {
+        match self {
+            Value::Text(_) | Value::Integer(_) => Some(key(self).into_owned()),
+            Value::Tag(_, v) => v.label(),
+            _ => None,
+        }
+    }
+}
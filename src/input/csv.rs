@@ -0,0 +1,36 @@
+use anyhow::*;
+use serde_json::{Map, Value};
+
+/// A CSV file is an array of rows, each row an object mapping the column
+/// headers to the field values.
+pub fn parse(content: &[u8], delimiter: u8) -> Result<Value> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content);
+    let headers = reader.headers()?.clone();
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            Ok(Value::Object(
+                record
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| {
+                        (
+                            // Fields beyond the header are keyed by their index
+                            headers
+                                .get(i)
+                                .map(str::to_owned)
+                                .unwrap_or_else(|| i.to_string()),
+                            Value::String(field.to_owned()),
+                        )
+                    })
+                    .collect::<Map<_, _>>(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Value::Array)
+}
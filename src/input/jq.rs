@@ -0,0 +1,58 @@
+use anyhow::*;
+use jaq_core::load::{Arena, File, Loader};
+use jaq_core::{data, unwrap_valr, Compiler, Ctx, Vars};
+use jaq_json::Val;
+use serde_json::Value;
+
+/// Run the jq `program` on `document`; if it yields several values, they are
+/// collected in an array.
+pub fn filter(document: Value, program: &str) -> Result<Value> {
+    let defs = jaq_core::defs()
+        .chain(jaq_std::defs())
+        .chain(jaq_json::defs());
+    let funs = jaq_core::funs()
+        .chain(jaq_std::funs())
+        .chain(jaq_json::funs());
+
+    let arena = Arena::default();
+    let modules = Loader::new(defs)
+        .load(
+            &arena,
+            File {
+                code: program,
+                path: (),
+            },
+        )
+        .map_err(|errs| {
+            anyhow!(
+                "invalid filter: {:?}",
+                errs.into_iter().map(|(_, e)| e).collect::<Vec<_>>()
+            )
+        })?;
+    let filter = Compiler::default()
+        .with_funs(funs)
+        .compile(modules)
+        .map_err(|errs| {
+            anyhow!(
+                "invalid filter: {:?}",
+                errs.into_iter().flat_map(|(_, e)| e).collect::<Vec<_>>()
+            )
+        })?;
+
+    let ctx = Ctx::<data::JustLut<Val>>::new(&filter.lut, Vars::new([]));
+    let mut values = filter
+        .id
+        .run((ctx, serde_json::from_value::<Val>(document)?))
+        .map(unwrap_valr)
+        .map(|v| {
+            let v = v.map_err(|e| anyhow!("while filtering: {}", e))?;
+            serde_json::from_str(&v.to_string())
+                .with_context(|| format!("`{}` can not be converted to JSON", v))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+    Ok(if values.len() == 1 {
+        values.remove(0)
+    } else {
+        Value::Array(values)
+    })
+}
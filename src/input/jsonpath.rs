@@ -0,0 +1,151 @@
+use super::{Document, Shape};
+use anyhow::*;
+
+/// A selector of a JSONPath step.
+#[derive(Debug)]
+enum Selector {
+    Key(String),
+    Index(isize),
+    Wildcard,
+}
+
+/// A step of a JSONPath, applying a selector to the current nodes or to all
+/// their descendants.
+#[derive(Debug)]
+struct Step {
+    selector: Selector,
+    descendants: bool,
+}
+
+/// Parse a JSONPath made of `.key`, `['key']`, `[index]`, `*` and `..`
+/// steps.
+fn parse(path: &str) -> Result<Vec<Step>> {
+    let mut rest = path
+        .trim()
+        .strip_prefix('$')
+        .ok_or_else(|| anyhow!("JSONPaths must start with `$`"))?;
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        let descendants = rest.starts_with("..");
+        if descendants {
+            rest = &rest[2..];
+        } else if let Some(r) = rest.strip_prefix('.') {
+            rest = r;
+        } else if !rest.starts_with('[') {
+            bail!("expected `.` or `[` at `{}`", rest);
+        }
+
+        let selector = if let Some(r) = rest.strip_prefix('[') {
+            let end = r
+                .find(']')
+                .ok_or_else(|| anyhow!("missing `]` in `{}`", path))?;
+            let inner = r[..end].trim();
+            rest = &r[end + 1..];
+            if inner == "*" {
+                Selector::Wildcard
+            } else if let Some(key) = inner
+                .strip_prefix('\'')
+                .and_then(|k| k.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|k| k.strip_suffix('"')))
+            {
+                Selector::Key(key.to_owned())
+            } else {
+                Selector::Index(
+                    inner
+                        .parse()
+                        .with_context(|| format!("invalid index `{}`", inner))?,
+                )
+            }
+        } else if let Some(r) = rest.strip_prefix('*') {
+            rest = r;
+            Selector::Wildcard
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            ensure!(end > 0, "missing key in `{}`", path);
+            let key = rest[..end].to_owned();
+            rest = &rest[end..];
+            Selector::Key(key)
+        };
+        steps.push(Step {
+            selector,
+            descendants,
+        });
+    }
+    Ok(steps)
+}
+
+/// The path of the child of `parent` at `key`.
+fn key_path(parent: &str, key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        format!("{}.{}", parent, key)
+    } else {
+        format!("{}['{}']", parent, key)
+    }
+}
+
+/// The children of `node`, along with their path.
+fn children<'a, D: Document>(path: &str, node: &'a D) -> Vec<(String, &'a D)> {
+    match node.shape() {
+        Shape::Scalar => Vec::new(),
+        Shape::Array(xs) => xs
+            .into_iter()
+            .enumerate()
+            .map(|(i, (_, x))| (format!("{}[{}]", path, i), x))
+            .collect(),
+        Shape::Object(kvs) => kvs
+            .into_iter()
+            .map(|(k, _, v)| (key_path(path, &k), v))
+            .collect(),
+    }
+}
+
+/// `node` and all its descendants, along with their path.
+fn descendants<'a, D: Document>(path: String, node: &'a D, ax: &mut Vec<(String, &'a D)>) {
+    // The nodes still to collect; the next one last
+    let mut stack = vec![(path, node)];
+    while let Some((path, node)) = stack.pop() {
+        stack.extend(children(&path, node).into_iter().rev());
+        ax.push((path, node));
+    }
+}
+
+/// All the nodes of `document` matching the JSONPath `path`, along with their
+/// normalized path.
+pub fn select<'a, D: Document>(document: &'a D, path: &str) -> Result<Vec<(String, &'a D)>> {
+    let mut current = vec![("$".to_owned(), document)];
+    for step in parse(path)? {
+        if step.descendants {
+            let mut all = Vec::new();
+            for (path, node) in current {
+                descendants(path, node, &mut all);
+            }
+            current = all;
+        }
+        current = current
+            .into_iter()
+            .flat_map(|(path, node)| match &step.selector {
+                Selector::Wildcard => children(&path, node),
+                Selector::Key(key) => match node.shape() {
+                    Shape::Object(kvs) => kvs
+                        .into_iter()
+                        .filter(|(k, _, _)| k == key)
+                        .map(|(k, _, v)| (key_path(&path, &k), v))
+                        .collect(),
+                    _ => Vec::new(),
+                },
+                Selector::Index(i) => match node.shape() {
+                    Shape::Array(xs) => {
+                        let i = if *i < 0 { xs.len() as isize + i } else { *i };
+                        usize::try_from(i)
+                            .ok()
+                            .and_then(|i| xs.get(i).map(|(_, x)| (format!("{}[{}]", path, i), *x)))
+                            .into_iter()
+                            .collect()
+                    }
+                    _ => Vec::new(),
+                },
+            })
+            .collect();
+    }
+    Ok(current)
+}
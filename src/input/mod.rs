@@ -0,0 +1,871 @@
+use crate::Node;
+use anyhow::*;
+pub use archive::is_archive;
+use clap::ValueEnum;
+pub use ndjson::parse_line;
+use serde::Deserialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::ops::Deref;
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod archive;
+mod avro;
+mod bson;
+mod cbor;
+mod csv;
+mod jq;
+mod jsonpath;
+mod msgpack;
+mod ndjson;
+mod parquet;
+mod protobuf;
+mod stream;
+mod toml;
+mod xml;
+mod yaml;
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum InputFormat {
+    Json,
+    Ndjson,
+    Yaml,
+    Toml,
+    #[value(name = "msgpack")]
+    MessagePack,
+    Cbor,
+    Bson,
+    Xml,
+    Csv,
+    Tsv,
+    Avro,
+    Parquet,
+    Protobuf,
+}
+impl InputFormat {
+    /// Guess the format of a file from its extension, defaulting to JSON.
+    pub fn from_path(path: &str) -> InputFormat {
+        InputFormat::from_extension(path).unwrap_or(InputFormat::Json)
+    }
+
+    fn from_extension(path: &str) -> Option<InputFormat> {
+        Some(match extension(path).as_deref() {
+            Some("json" | "json5" | "jsonc") => InputFormat::Json,
+            Some("ndjson" | "jsonl") => InputFormat::Ndjson,
+            Some("yaml" | "yml") => InputFormat::Yaml,
+            Some("toml") => InputFormat::Toml,
+            Some("msgpack" | "mpk") => InputFormat::MessagePack,
+            Some("cbor") => InputFormat::Cbor,
+            Some("bson") => InputFormat::Bson,
+            Some("xml") => InputFormat::Xml,
+            Some("csv") => InputFormat::Csv,
+            Some("tsv" | "tab") => InputFormat::Tsv,
+            Some("avro") => InputFormat::Avro,
+            Some("parquet") => InputFormat::Parquet,
+            Some("binpb") => InputFormat::Protobuf,
+            _ => return None,
+        })
+    }
+
+    /// Whether documents in this format are decoded to JSON values.
+    fn is_json_like(&self) -> bool {
+        matches!(
+            self,
+            InputFormat::Json
+                | InputFormat::Ndjson
+                | InputFormat::Yaml
+                | InputFormat::Toml
+                | InputFormat::Xml
+                | InputFormat::Csv
+                | InputFormat::Tsv
+        )
+    }
+
+    pub fn load(&self, content: &[u8], settings: &ParseSettings, tag: String) -> Result<Node> {
+        let relaxed = settings.relaxed;
+        ensure!(
+            settings.filter.is_none() || self.is_json_like(),
+            "--filter is not supported for {:?} inputs",
+            self
+        );
+        match self {
+            InputFormat::Json if stream::supports(settings) => {
+                stream::parse(content, settings, tag)
+            }
+            InputFormat::Ndjson if stream::supports(settings) => {
+                stream::parse_lines(content, settings, tag)
+            }
+            InputFormat::Json => {
+                // Leave concatenated documents & syntax errors to serde_json
+                let fast = (settings.fast && !relaxed)
+                    .then(|| simd_json::serde::from_slice(&mut content.to_vec()).ok())
+                    .flatten();
+                let document = match fast {
+                    Some(document) => document,
+                    None => parse_json(
+                        std::str::from_utf8(content)?,
+                        relaxed,
+                        settings.max_parse_depth.is_some(),
+                    )?,
+                };
+                json(document, settings, tag)
+            }
+            InputFormat::Ndjson => json(
+                ndjson::parse(
+                    std::str::from_utf8(content)?,
+                    relaxed,
+                    settings.max_parse_depth.is_some(),
+                )?,
+                settings,
+                tag,
+            ),
+            InputFormat::Yaml => json(yaml::parse(std::str::from_utf8(content)?)?, settings, tag),
+            InputFormat::Toml => json(toml::parse(std::str::from_utf8(content)?)?, settings, tag),
+            InputFormat::MessagePack => build(&msgpack::parse(content)?, settings, tag),
+            InputFormat::Cbor => build(&cbor::parse(content)?, settings, tag),
+            InputFormat::Bson => bson::load(content, settings, tag),
+            InputFormat::Xml => json(xml::parse(std::str::from_utf8(content)?)?, settings, tag),
+            InputFormat::Csv => json(csv::parse(content, b',')?, settings, tag),
+            InputFormat::Tsv => json(csv::parse(content, b'\t')?, settings, tag),
+            InputFormat::Avro => build(&avro::parse(content)?, settings, tag),
+            InputFormat::Parquet => build(
+                &parquet::parse(content, settings.uncompressed)?,
+                settings,
+                tag,
+            ),
+            InputFormat::Protobuf => build(
+                &protobuf::parse(
+                    content,
+                    settings
+                        .proto_descriptor
+                        .as_deref()
+                        .ok_or_else(|| anyhow!("decoding protobuf requires --proto-descriptor"))?,
+                    settings
+                        .message_type
+                        .as_deref()
+                        .ok_or_else(|| anyhow!("decoding protobuf requires --message-type"))?,
+                )?,
+                settings,
+                tag,
+            ),
+        }
+    }
+}
+
+/// Build the size tree of a JSON `document`, once transformed by the jq
+/// `filter` of `settings` if any.
+fn json(document: Value, settings: &ParseSettings, tag: String) -> Result<Node> {
+    let document = match &settings.filter {
+        Some(program) => jq::filter(document, program)?,
+        None => document,
+    };
+    let root = build(&document, settings, tag);
+    if settings.max_parse_depth.is_some() {
+        discard(document);
+    }
+    root
+}
+
+/// Drop a document without recursion, as it may be arbitrarily deep.
+fn discard(document: Value) {
+    let mut stack = vec![document];
+    while let Some(value) = stack.pop() {
+        match value {
+            Value::Array(xs) => stack.extend(xs),
+            Value::Object(kvs) => stack.extend(kvs.into_iter().map(|(_, v)| v)),
+            _ => {}
+        }
+    }
+}
+
+/// Build the size tree of `document`, or of its subtree designated by the
+/// `root` pointer of `settings`; if `settings` has a JSONPath selection, the
+/// matching nodes are gathered under a synthetic root.
+fn build<D: Document>(document: &D, settings: &ParseSettings, tag: String) -> Result<Node> {
+    if let Some(max) = settings.max_parse_depth {
+        check_depth(document, max)?;
+    }
+    let mut current = document;
+    if let Some(pointer) = &settings.root {
+        ensure!(
+            pointer.is_empty() || pointer.starts_with('/'),
+            "JSON pointers must be empty or start with `/`"
+        );
+        for token in pointer.split('/').skip(1) {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            current = match current.shape() {
+                Shape::Object(children) => children
+                    .into_iter()
+                    .find(|(k, _, _)| *k == token)
+                    .map(|(_, _, c)| c),
+                Shape::Array(children) => token
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| children.get(i))
+                    .map(|(_, c)| *c),
+                Shape::Scalar => None,
+            }
+            .ok_or_else(|| anyhow!("`{}` does not exist in the document", pointer))?;
+        }
+    }
+    Ok(match &settings.select {
+        Some(path) => Node::object(
+            tag,
+            0,
+            jsonpath::select(current, path)?
+                .into_iter()
+                .map(|(path, node)| Node::from_document(node, 1, path, settings))
+                .collect(),
+        ),
+        None => Node::from_document(current, 0, tag, settings),
+    })
+}
+
+pub struct ParseSettings {
+    /// Whether JSON inputs may contain JSON5 extensions such as comments,
+    /// trailing commas, or unquoted keys
+    pub relaxed: bool,
+    /// Whether to parse JSON inputs with simd-json
+    pub fast: bool,
+    /// Whether Parquet columns should be weighted by their uncompressed size
+    pub uncompressed: bool,
+    /// The file holding the protobuf `FileDescriptorSet`
+    pub proto_descriptor: Option<String>,
+    /// The fully-qualified name of the protobuf message to decode
+    pub message_type: Option<String>,
+    /// Whether to estimate the compressed size of every subtree
+    pub compressed: bool,
+    /// The tokenizer with which to count the tokens of every subtree, if any
+    pub tokenizer: Option<crate::tokens::Tokenizer>,
+    /// The JSON pointer to the subtree to analyze, if not the whole document
+    pub root: Option<String>,
+    /// The jq program to transform JSON documents with before analysis
+    pub filter: Option<String>,
+    /// The JSONPath of the nodes to analyze, if not the whole document
+    pub select: Option<String>,
+    /// Whether to keep the elements of arrays as children
+    pub expand_arrays: bool,
+    /// Whether to aggregate the elements of arrays of objects as a single
+    /// child
+    pub element_schema: bool,
+    /// How many elements of every array to keep as children or in schemas,
+    /// if not all
+    pub sample: Option<usize>,
+    /// The field labelling the object elements of expanded arrays
+    pub id_field: Option<String>,
+    /// Only the entries whose key or a descendant key matches one of these
+    /// are analyzed, if any
+    pub include_keys: Vec<regex::Regex>,
+    /// The entries whose key matches one of these are ignored
+    pub exclude_keys: Vec<regex::Regex>,
+    /// Whether to count the distinct scalar values of every subtree
+    pub distinct: bool,
+    /// How many characters of the scalar values to keep for previews
+    pub preview: Option<usize>,
+    /// The pattern to match the scalar values against
+    pub grep: Option<regex::Regex>,
+    /// The bar tracking the progress of parsing, for large inputs
+    pub progress: Option<indicatif::ProgressBar>,
+    /// The tags of the nodes built so far
+    pub tags: crate::Tags,
+    /// The depth beyond which nodes are weighed but not kept, if any
+    pub max_depth: Option<usize>,
+    /// The nodes lighter than this in this unit are kept without their
+    /// descendants, if any
+    pub min_size: Option<(crate::Unit, usize)>,
+    /// The nodes lighter than this ratio of the whole document in this unit
+    /// are kept without their descendants, if any
+    pub threshold: Option<(crate::Unit, f32)>,
+    /// The size, in the unit of the threshold, of the scalars weighed so
+    /// far; the whole document weighs at least as much
+    pub weighed: AtomicUsize,
+    /// The depth beyond which documents may not nest values, if any
+    pub max_parse_depth: Option<usize>,
+}
+
+/// The content of an input, either mapped from a file or read in memory.
+pub enum Content {
+    Mapped(memmap2::Mmap),
+    Read(Vec<u8>),
+}
+impl Deref for Content {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Content::Mapped(m) => m,
+            Content::Read(r) => r,
+        }
+    }
+}
+impl From<Vec<u8>> for Content {
+    fn from(content: Vec<u8>) -> Self {
+        Content::Read(content)
+    }
+}
+
+/// Map a file in memory, or read it if it can not be mapped, e.g. if it is
+/// empty or not a regular file.
+fn map(path: &str) -> Result<Content> {
+    let file = std::fs::File::open(path)?;
+    // SAFETY: the mapping only lives while the file is being parsed, and
+    // files expected to change meanwhile are not mapped; still, should the
+    // file be truncated by another process, reading it would raise SIGBUS.
+    match unsafe { memmap2::Mmap::map(&file) } {
+        Result::Ok(m) if !m.is_empty() => Ok(Content::Mapped(m)),
+        _ => Ok(std::fs::read(path)?.into()),
+    }
+}
+
+impl ParseSettings {
+    /// Record that `bytes` more bytes of the input have been parsed.
+    pub fn advance(&self, bytes: usize) {
+        if let Some(progress) = &self.progress {
+            progress.inc(bytes as u64);
+        }
+    }
+
+    /// Forget what has been recorded while parsing the input, before it is
+    /// parsed again from the start.
+    pub fn rewind(&self) {
+        if let Some(progress) = &self.progress {
+            progress.set_position(0);
+        }
+        self.weighed.store(0, Ordering::Relaxed);
+        self.tags.0.lock().unwrap().clear();
+    }
+}
+
+/// Read a file, stdin if `path` is `-`, or download it if `path` is an
+/// HTTP(S) URL; in the latter case, `headers` are added to the request.
+/// Files within archives are read from `archive::member` paths. Local files
+/// are mapped in memory if `mapped`, which they must not be if they may be
+/// truncated while parsed.
+pub fn read(path: &str, headers: &[String], mapped: bool) -> Result<Content> {
+    let mut r = Vec::new();
+    if let Some((archive, member)) = path.split_once("::") {
+        r = archive::members(archive, read(archive, headers, mapped)?, |name| {
+            name == member
+        })?
+        .pop()
+        .ok_or_else(|| anyhow!("`{}` not found in `{}`", member, archive))?
+        .1;
+    } else if path == "-" {
+        std::io::stdin().read_to_end(&mut r)?;
+    } else if is_url(path) {
+        let mut request = ureq::get(path);
+        for header in headers {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| anyhow!("invalid header `{}`; expected `Name: value`", header))?;
+            request = request.header(name.trim(), value.trim());
+        }
+        request
+            .call()?
+            .into_body()
+            .into_reader()
+            .read_to_end(&mut r)?;
+    } else if mapped {
+        return map(path);
+    } else {
+        r = std::fs::read(path)?;
+    }
+    Ok(r.into())
+}
+
+/// Read the version of the local file `path` committed at the git
+/// `revision`.
+pub fn read_revision(path: &str, revision: &str) -> Result<Content> {
+    let path = Path::new(path);
+    let directory = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("`{}` is not a file", path.display()))?;
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .arg("cat-file")
+        .arg("blob")
+        .arg(format!("{}:./{}", revision, name.to_string_lossy()))
+        .output()
+        .context("while running git")?;
+    ensure!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(output.stdout.into())
+}
+
+/// Expand globs and, if `recursive` is set, directories in `paths` to the
+/// files they contain. Directories are only searched for archives and files
+/// in one of the supported formats.
+pub fn expand(paths: &[String], recursive: bool) -> Result<Vec<String>> {
+    fn walk(path: &Path, recursive: bool, r: &mut Vec<String>) -> Result<()> {
+        if path.is_dir() {
+            ensure!(
+                recursive,
+                "`{}` is a directory; use --recursive to analyze its content",
+                path.display()
+            );
+            let mut entries = std::fs::read_dir(path)
+                .with_context(|| format!("while reading `{}`", path.display()))?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            entries.sort();
+            for entry in entries {
+                if entry.is_dir() || InputFormat::from_extension(&entry.to_string_lossy()).is_some()
+                {
+                    walk(&entry, recursive, r)?;
+                }
+            }
+        } else {
+            r.push(path.to_string_lossy().into_owned());
+        }
+        Ok(())
+    }
+
+    let mut r = Vec::new();
+    for path in paths {
+        if path == "-" || is_url(path) {
+            r.push(path.to_owned());
+        } else if path.contains(['*', '?', '[']) && !Path::new(path).exists() {
+            let before = r.len();
+            for entry in glob::glob(path)? {
+                walk(&entry?, recursive, &mut r)?;
+            }
+            ensure!(r.len() > before, "no file matches `{}`", path);
+        } else {
+            walk(Path::new(path), recursive, &mut r)?;
+        }
+    }
+    Ok(r)
+}
+
+/// Extract the files in one of the supported formats from an archive, as
+/// `archive::member` paths and their content.
+pub fn unpack(path: &str, content: Content) -> Result<Vec<(String, Content)>> {
+    Ok(archive::members(path, content, |name| {
+        InputFormat::from_extension(name).is_some()
+    })?
+    .into_iter()
+    .map(|(name, content)| (format!("{}::{}", path, name), content.into()))
+    .collect())
+}
+
+/// Gather the documents parsed from several files in a tree mirroring their
+/// location in the filesystem; archives are considered as directories.
+pub fn corpus(files: Vec<(String, Node)>, tag: String) -> Node {
+    enum Entry {
+        Directory(BTreeMap<String, Entry>),
+        File(Node),
+    }
+
+    fn to_node(name: String, entry: Entry) -> Node {
+        match entry {
+            Entry::Directory(children) => Node::object(
+                name,
+                0,
+                children
+                    .into_iter()
+                    .map(|(name, e)| to_node(name, e))
+                    .collect(),
+            ),
+            Entry::File(node) => node,
+        }
+    }
+
+    let mut root = BTreeMap::new();
+    for (path, node) in files {
+        let path = path.replace("::", "/");
+        let components = Path::new(&path)
+            .components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let Some((file, directories)) = components.split_last() else {
+            continue;
+        };
+        let mut current = &mut root;
+        for directory in directories {
+            let entry = current
+                .entry(directory.to_owned())
+                .or_insert_with(|| Entry::Directory(BTreeMap::new()));
+            current = match entry {
+                Entry::Directory(children) => children,
+                Entry::File(_) => unreachable!("a file can not be a directory"),
+            };
+        }
+        current.insert(file.to_owned(), Entry::File(node));
+    }
+
+    to_node(tag, Entry::Directory(root))
+}
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Whether a file extension hints at JSON with comments or JSON5.
+pub fn is_relaxed(path: &str) -> bool {
+    matches!(extension(path).as_deref(), Some("json5" | "jsonc"))
+}
+
+/// The extension of a file, ignoring the one of the compression format if
+/// any, e.g. `json` for `data.json.gz`.
+fn extension(path: &str) -> Option<String> {
+    let path = if is_url(path) {
+        Path::new(path.split(['?', '#']).next().unwrap_or_default())
+    } else {
+        Path::new(path)
+    };
+    let extension = |p: &Path| {
+        p.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+    };
+    match extension(path).as_deref() {
+        Some("gz" | "zst" | "xz" | "bz2") => {
+            path.file_stem().and_then(|stem| extension(Path::new(stem)))
+        }
+        _ => extension(path),
+    }
+}
+
+/// Decompress gzip, zstd, xz, and bzip2 contents, as identified by their
+/// magic number; other contents are returned untouched.
+pub fn decompress(content: Content) -> Result<Content> {
+    let mut r = Vec::new();
+    match &*content {
+        [0x1f, 0x8b, ..] => {
+            flate2::read::MultiGzDecoder::new(&*content)
+                .read_to_end(&mut r)
+                .context("while decompressing gzip")?;
+        }
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => {
+            ruzstd::decoding::StreamingDecoder::new(&*content)
+                .map_err(|e| anyhow!("{}", e))
+                .and_then(|mut d| Ok(d.read_to_end(&mut r)?))
+                .context("while decompressing zstd")?;
+        }
+        [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => {
+            lzma_rs::xz_decompress(&mut &*content, &mut r)
+                .map_err(|e| anyhow!("{:?}", e))
+                .context("while decompressing xz")?;
+        }
+        [b'B', b'Z', b'h', ..] => {
+            bzip2::read::MultiBzDecoder::new(&*content)
+                .read_to_end(&mut r)
+                .context("while decompressing bzip2")?;
+        }
+        _ => return Ok(content),
+    }
+    Ok(r.into())
+}
+
+/// Parse a JSON document; streams of several back-to-back documents are read
+/// as an array of documents. Unless `unbounded`, documents may not nest
+/// deeper than the 128 levels allowed by serde_json.
+fn parse_json(content: &str, relaxed: bool, unbounded: bool) -> Result<Value> {
+    if relaxed {
+        return Ok(json5::from_str(content)?);
+    }
+
+    let mut documents = if unbounded {
+        // The stack grows as needed; the depth is checked once parsed
+        let mut de = serde_json::Deserializer::from_str(content);
+        de.disable_recursion_limit();
+        let mut documents = Vec::new();
+        while de.end().is_err() {
+            documents.push(Value::deserialize(serde_stacker::Deserializer::new(
+                &mut de,
+            ))?);
+        }
+        documents
+    } else {
+        serde_json::Deserializer::from_str(content)
+            .into_iter::<Value>()
+            .collect::<serde_json::Result<Vec<_>>>()?
+    };
+    Ok(match documents.len() {
+        0 => bail!("no JSON document found"),
+        1 => documents.pop().unwrap(),
+        _ => Value::Array(documents),
+    })
+}
+
+/// Fail if `document` nests containers deeper than `max` levels.
+fn check_depth<D: Document>(document: &D, max: usize) -> Result<()> {
+    let mut stack = vec![(document, 0, String::new())];
+    while let Some((node, depth, path)) = stack.pop() {
+        let children: Vec<_> = match node.shape() {
+            Shape::Scalar => continue,
+            Shape::Array(children) => children
+                .into_iter()
+                .enumerate()
+                .map(|(i, (_, c))| (c, i.to_string().into()))
+                .collect(),
+            Shape::Object(children) => children.into_iter().map(|(k, _, c)| (c, k)).collect(),
+        };
+        ensure!(depth < max, too_deep(max, &path));
+        for (child, key) in children {
+            stack.push((child, depth + 1, crate::output::child_path(&path, &key)));
+        }
+    }
+    Ok(())
+}
+
+/// The error raised when a document nests containers deeper than `max`
+/// levels, at the JSON pointer `path`.
+fn too_deep(max: usize, path: &str) -> String {
+    format!(
+        "the document is nested deeper than {} levels at `{}`",
+        max,
+        if path.is_empty() { "/" } else { path }
+    )
+}
+
+/// How a document node should be treated when building the size tree.
+pub enum Shape<'a, D> {
+    Scalar,
+    /// The elements of an array, along with the size taken by their implicit
+    /// key, if any, in the document encoding.
+    Array(Vec<(usize, &'a D)>),
+    /// The children of an object, along with their key and the size of this
+    /// key in the document encoding.
+    Object(Vec<(Cow<'a, str>, usize, &'a D)>),
+}
+
+/// The type of a value, as in JSON.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+    Null,
+    Binary,
+    /// Anything else, or values of mixed types
+    Other,
+}
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Object => "{}",
+            Kind::Array => "[]",
+            Kind::String => "str",
+            Kind::Number => "num",
+            Kind::Bool => "bool",
+            Kind::Null => "null",
+            Kind::Binary => "bin",
+            Kind::Other => "?",
+        }
+    }
+}
+
+/// A parsed document, in any of the supported input formats.
+pub trait Document: Sized + Sync {
+    fn shape(&self) -> Shape<'_, Self>;
+
+    /// The type of a scalar, if known.
+    fn kind(&self) -> Kind {
+        Kind::Other
+    }
+
+    /// The size of a scalar in the document encoding.
+    fn size(&self) -> usize;
+
+    /// The size of the syntax of this value in the document encoding, e.g.
+    /// quotes, brackets or separators, that is not part of its size nor of
+    /// the size of its keys and children.
+    fn overhead(&self) -> usize {
+        0
+    }
+
+    /// The heap memory owned by a scalar once deserialized.
+    fn heap(&self) -> usize {
+        0
+    }
+
+    /// The size of a scalar in characters for strings, or as `size`
+    /// otherwise.
+    fn chars(&self) -> usize {
+        self.size()
+    }
+
+    /// The minified serialization of this value in the document encoding,
+    /// if available.
+    fn serialize(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// A representation of a scalar that only equals those of the same
+    /// value, if available.
+    fn identity(&self) -> Option<Vec<u8>> {
+        self.serialize()
+    }
+
+    /// The value of a string or numeric scalar as text, to label the
+    /// element it identifies.
+    fn label(&self) -> Option<String> {
+        None
+    }
+
+    /// The value of a scalar as text, to preview it.
+    fn preview(&self) -> Option<String> {
+        self.label()
+    }
+}
+
+impl Document for Value {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            Value::Array(xs) => Shape::Array(xs.iter().map(|x| (0, x)).collect()),
+            Value::Object(kvs) => Shape::Object(
+                kvs.iter()
+                    .map(|(k, v)| (Cow::Borrowed(k.as_str()), k.len(), v))
+                    .collect(),
+            ),
+            _ => Shape::Scalar,
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Array(_) => Kind::Array,
+            Value::Object(_) => Kind::Object,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Value::Null => 0,
+            Value::Bool(_) => 4,
+            Value::Number(x) => x.to_string().len(),
+            Value::String(s) => s.len(),
+            Value::Array(_) | Value::Object(_) => 0,
+        }
+    }
+
+    fn overhead(&self) -> usize {
+        match self {
+            Value::Null => 4,
+            Value::Bool(b) => usize::from(!b),
+            Value::Number(_) => 0,
+            Value::String(s) => quoted_len(s) - s.len(),
+            Value::Array(xs) => 2 + xs.len().saturating_sub(1),
+            Value::Object(kvs) => {
+                2 + kvs.len().saturating_sub(1)
+                    + kvs
+                        .keys()
+                        .map(|k| quoted_len(k) - k.len() + 1)
+                        .sum::<usize>()
+            }
+        }
+    }
+
+    fn heap(&self) -> usize {
+        match self {
+            Value::String(s) => crate::memory::alloc(s.len()),
+            _ => 0,
+        }
+    }
+
+    fn chars(&self) -> usize {
+        match self {
+            Value::String(s) => s.chars().count(),
+            v => v.size(),
+        }
+    }
+
+    fn serialize(&self) -> Option<Vec<u8>> {
+        serde_json::to_vec(self).ok()
+    }
+
+    fn label(&self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(x) => Some(x.to_string()),
+            _ => None,
+        }
+    }
+
+    fn preview(&self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(s.clone()),
+            Value::Array(_) | Value::Object(_) => None,
+            x => Some(x.to_string()),
+        }
+    }
+}
+
+/// The length of `s` as a JSON string, quotes and escapes included.
+fn quoted_len(s: &str) -> usize {
+    serde_json::to_string(s).map_or(s.len() + 2, |q| q.len())
+}
+
+/// A document whose sizes have been computed while decoding it, for formats
+/// where they depend on context that is lost once decoded.
+pub enum Measured {
+    Scalar(usize),
+    Array(Vec<(usize, Measured)>),
+    Object(Vec<(String, usize, Measured)>),
+}
+impl Document for Measured {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            Measured::Scalar(_) => Shape::Scalar,
+            Measured::Array(xs) => Shape::Array(xs.iter().map(|(ks, x)| (*ks, x)).collect()),
+            Measured::Object(kvs) => Shape::Object(
+                kvs.iter()
+                    .map(|(k, ks, v)| (Cow::Borrowed(k.as_str()), *ks, v))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Measured::Scalar(size) => *size,
+            Measured::Array(_) | Measured::Object(_) => 0,
+        }
+    }
+}
+
+/// The size of `content` once gzipped, excluding the constant size of the
+/// gzip header & trailer.
+pub fn compressed_size(content: &[u8]) -> usize {
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Counter(0), flate2::Compression::default());
+    encoder
+        .write_all(content)
+        .expect("writing to a counter can not fail");
+    encoder
+        .finish()
+        .expect("writing to a counter can not fail")
+        .0
+}
+
+/// Count the bytes written to it, without storing them.
+struct Counter(usize);
+impl std::io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        std::io::Result::Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::Result::Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use super::{Counter, Document, Kind, Shape};
+use anyhow::*;
+use rmpv::Value;
+use std::borrow::Cow;
+
+pub fn parse(content: &[u8]) -> Result<Value> {
+    Ok(rmpv::decode::read_value(&mut &content[..])?)
+}
+
+/// The size of a value once re-encoded; as encoders use the most compact
+/// representation available, this should match the original document.
+fn encoded_size(v: &Value) -> usize {
+    let mut counter = Counter(0);
+    rmpv::encode::write_value(&mut counter, v).expect("writing to a counter can not fail");
+    counter.0
+}
+
+/// The size of the header of a container of `len` elements: its marker,
+/// followed by its length unless packed in the marker.
+fn header(len: usize) -> usize {
+    match len {
+        0..16 => 1,
+        16..65536 => 3,
+        _ => 5,
+    }
+}
+
+impl Document for Value {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            Value::Array(xs) => Shape::Array(xs.iter().map(|x| (0, x)).collect()),
+            Value::Map(kvs) => Shape::Object(
+                kvs.iter()
+                    .map(|(k, v)| {
+                        let key = match k {
+                            Value::String(s) if s.as_str().is_some() => {
+                                Cow::Borrowed(s.as_str().unwrap())
+                            }
+                            k => Cow::Owned(k.to_string()),
+                        };
+                        (key, encoded_size(k), v)
+                    })
+                    .collect(),
+            ),
+            _ => Shape::Scalar,
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Value::Nil => Kind::Null,
+            Value::Boolean(_) => Kind::Bool,
+            Value::Integer(_) | Value::F32(_) | Value::F64(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Binary(_) => Kind::Binary,
+            Value::Array(_) => Kind::Array,
+            Value::Map(_) => Kind::Object,
+            Value::Ext(..) => Kind::Other,
+        }
+    }
+
+    fn size(&self) -> usize {
+        encoded_size(self)
+    }
+
+    fn overhead(&self) -> usize {
+        match self {
+            Value::Array(xs) => header(xs.len()),
+            Value::Map(kvs) => header(kvs.len()),
+            _ => 0,
+        }
+    }
+
+    fn chars(&self) -> usize {
+        match self {
+            Value::String(s) if s.as_str().is_some() => s.as_str().unwrap().chars().count(),
+            v => v.size(),
+        }
+    }
+
+    fn serialize(&self) -> Option<Vec<u8>> {
+        let mut content = Vec::new();
+        rmpv::encode::write_value(&mut content, self).ok()?;
+        Some(content)
+    }
+
+    fn label(&self) -> Option<String> {
+        match self {
+            Value::String(s) => s.as_str().map(str::to_owned),
+            Value::Integer(i) => Some(i.to_string()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use super::{json, parse_json, ParseSettings};
+use crate::Node;
+use anyhow::*;
+use serde_json::Value;
+
+/// Each non-empty line of an NDJSON file becomes an element of the root
+/// array.
+pub fn parse(content: &str, relaxed: bool, unbounded: bool) -> Result<Value> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            parse_json(l, relaxed, unbounded)
+                .with_context(|| format!("while parsing line {}", i + 1))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Value::Array)
+}
+
+/// Parse a single line of an NDJSON file into a size tree.
+pub fn parse_line(line: &str, settings: &ParseSettings) -> Result<Node> {
+    json(
+        parse_json(line, settings.relaxed, settings.max_parse_depth.is_some())?,
+        settings,
+        String::new(),
+    )
+}
@@ -0,0 +1,41 @@
+use super::Measured;
+use anyhow::*;
+use parquet::file::metadata::ParquetMetaDataReader;
+
+/// Describe a Parquet file as its row groups, each of them made of its
+/// column chunks weighted by their compressed or uncompressed size.
+pub fn parse(content: &[u8], uncompressed: bool) -> Result<Measured> {
+    let metadata =
+        ParquetMetaDataReader::new().parse_and_finish(&bytes::Bytes::copy_from_slice(content))?;
+
+    Ok(Measured::Object(
+        metadata
+            .row_groups()
+            .iter()
+            .enumerate()
+            .map(|(i, rg)| {
+                (
+                    format!("row group #{} ({} rows)", i, rg.num_rows()),
+                    0,
+                    Measured::Object(
+                        rg.columns()
+                            .iter()
+                            .map(|c| {
+                                let size = if uncompressed {
+                                    c.uncompressed_size()
+                                } else {
+                                    c.compressed_size()
+                                };
+                                (
+                                    c.column_path().string(),
+                                    0,
+                                    Measured::Scalar(size.max(0) as usize),
+                                )
+                            })
+                            .collect(),
+                    ),
+                )
+            })
+            .collect(),
+    ))
+}
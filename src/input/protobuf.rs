@@ -0,0 +1,167 @@
+use super::Measured;
+use anyhow::*;
+use prost_reflect::{DescriptorPool, DynamicMessage, Kind, MapKey, Value};
+
+/// Decode a binary protobuf message of the given type, described in a
+/// `FileDescriptorSet` as produced by `protoc --descriptor_set_out`.
+pub fn parse(content: &[u8], descriptor: &str, message_type: &str) -> Result<Measured> {
+    let pool = DescriptorPool::decode(
+        std::fs::read(descriptor)
+            .with_context(|| format!("while reading `{}`", descriptor))?
+            .as_slice(),
+    )
+    .with_context(|| format!("while decoding `{}`", descriptor))?;
+    let message = pool.get_message_by_name(message_type).ok_or_else(|| {
+        anyhow!(
+            "message type `{}` not found in `{}`",
+            message_type,
+            descriptor
+        )
+    })?;
+
+    Ok(from_message(&DynamicMessage::decode(message, content)?).0)
+}
+
+fn varint(n: u64) -> usize {
+    (64 - n.leading_zeros() as usize).max(1).div_ceil(7)
+}
+
+/// The size of a field key, i.e. its number and its wire type.
+fn key(number: u32) -> usize {
+    varint((number as u64) << 3)
+}
+
+fn scalar_size(v: &Value, kind: &Kind) -> usize {
+    match (kind, v) {
+        (Kind::Sint32, Value::I32(x)) => varint(((x << 1) ^ (x >> 31)) as u32 as u64),
+        (Kind::Sint64, Value::I64(x)) => varint(((x << 1) ^ (x >> 63)) as u64),
+        (Kind::Fixed32 | Kind::Sfixed32 | Kind::Float, _) => 4,
+        (Kind::Fixed64 | Kind::Sfixed64 | Kind::Double, _) => 8,
+        (_, Value::Bool(_)) => 1,
+        // Negative int32 are sign-extended to 64 bits
+        (_, Value::I32(x) | Value::EnumNumber(x)) => varint(*x as i64 as u64),
+        (_, Value::I64(x)) => varint(*x as u64),
+        (_, Value::U32(x)) => varint(*x as u64),
+        (_, Value::U64(x)) => varint(*x),
+        (_, Value::String(s)) => varint(s.len() as u64) + s.len(),
+        (_, Value::Bytes(b)) => varint(b.len() as u64) + b.len(),
+        (_, Value::F32(_)) => 4,
+        (_, Value::F64(_)) => 8,
+        (_, Value::Message(_) | Value::List(_) | Value::Map(_)) => 0,
+    }
+}
+
+fn map_key_size(k: &MapKey, kind: &Kind) -> usize {
+    let v = match k {
+        MapKey::Bool(b) => Value::Bool(*b),
+        MapKey::I32(x) => Value::I32(*x),
+        MapKey::I64(x) => Value::I64(*x),
+        MapKey::U32(x) => Value::U32(*x),
+        MapKey::U64(x) => Value::U64(*x),
+        MapKey::String(s) => return varint(s.len() as u64) + s.len(),
+    };
+    scalar_size(&v, kind)
+}
+
+fn map_key_name(k: &MapKey) -> String {
+    match k {
+        MapKey::Bool(b) => b.to_string(),
+        MapKey::I32(x) => x.to_string(),
+        MapKey::I64(x) => x.to_string(),
+        MapKey::U32(x) => x.to_string(),
+        MapKey::U64(x) => x.to_string(),
+        MapKey::String(s) => s.clone(),
+    }
+}
+
+/// The measure of a value, and its encoded length.
+fn from_value(v: &Value, kind: &Kind) -> (Measured, usize) {
+    match v {
+        Value::Message(m) => from_message(m),
+        v => {
+            let size = scalar_size(v, kind);
+            (Measured::Scalar(size), size)
+        }
+    }
+}
+
+/// The length prefix of a value, if it is a message.
+fn prefix(v: &Value, len: usize) -> usize {
+    match v {
+        Value::Message(_) => varint(len as u64),
+        _ => 0,
+    }
+}
+
+/// The measure of a message, and its encoded length.
+///
+/// The keys and length prefixes of the fields, of the elements of repeated
+/// fields and of map entries are accounted as key size.
+fn from_message(m: &DynamicMessage) -> (Measured, usize) {
+    let mut total = 0;
+    let fields = m
+        .fields()
+        .map(|(f, v)| {
+            let kind = f.kind();
+            let (key_size, value, len) = match v {
+                // Packed lists share a single key & length prefix
+                Value::List(xs) if f.is_packed() => {
+                    let elements = xs
+                        .iter()
+                        .map(|x| (0, from_value(x, &kind).0))
+                        .collect::<Vec<_>>();
+                    let len = xs.iter().map(|x| scalar_size(x, &kind)).sum::<usize>();
+                    (
+                        key(f.number()) + varint(len as u64),
+                        Measured::Array(elements),
+                        len,
+                    )
+                }
+                Value::List(xs) => {
+                    let mut len = 0;
+                    let elements = xs
+                        .iter()
+                        .map(|x| {
+                            let (value, value_len) = from_value(x, &kind);
+                            let key_size = key(f.number()) + prefix(x, value_len);
+                            len += key_size + value_len;
+                            (key_size, value)
+                        })
+                        .collect();
+                    (0, Measured::Array(elements), len)
+                }
+                // Map entries are length-prefixed messages made of a key and
+                // a value field, with single-byte keys
+                Value::Map(kvs) => {
+                    let entry = kind.as_message().expect("map fields are messages");
+                    let key_kind = entry.map_entry_key_field().kind();
+                    let value_kind = entry.map_entry_value_field().kind();
+                    let mut len = 0;
+                    let entries = kvs
+                        .iter()
+                        .map(|(k, v)| {
+                            let (value, value_len) = from_value(v, &value_kind);
+                            let entry_len = 1
+                                + map_key_size(k, &key_kind)
+                                + 1
+                                + prefix(v, value_len)
+                                + value_len;
+                            let key_size =
+                                key(f.number()) + varint(entry_len as u64) + entry_len - value_len;
+                            len += key_size + value_len;
+                            (map_key_name(k), key_size, value)
+                        })
+                        .collect();
+                    (0, Measured::Object(entries), len)
+                }
+                v => {
+                    let (value, len) = from_value(v, &kind);
+                    (key(f.number()) + prefix(v, len), value, len)
+                }
+            };
+            total += key_size + len;
+            (f.name().to_owned(), key_size, value)
+        })
+        .collect();
+    (Measured::Object(fields), total)
+}
@@ -0,0 +1,371 @@
+//! Build the size tree of JSON and NDJSON documents while parsing them,
+//! without materializing them as `serde_json::Value` first; only the scalars
+//! are, one at a time. The elements of arrays are aggregated as they come, so
+//! that documents larger than memory can be analyzed.
+use super::{quoted_len, Document, ParseSettings};
+use crate::{output, Elements, Node, Tag};
+use anyhow::{ensure, Context, Result};
+use serde::de::{DeserializeSeed, Deserializer, Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use std::fmt;
+
+/// Whether the size tree of JSON documents can be built by [`parse`] with
+/// these settings; the others require the whole document.
+pub fn supports(settings: &ParseSettings) -> bool {
+    !settings.relaxed
+        && settings.filter.is_none()
+        && settings.root.is_none()
+        && settings.select.is_none()
+        && !settings.compressed
+        && settings.tokenizer.is_none()
+}
+
+/// Build the size tree of the JSON documents in `content`; several
+/// concatenated documents are handled as an array.
+pub fn parse(content: &[u8], settings: &ParseSettings, tag: String) -> Result<Node> {
+    ensure!(
+        content.iter().any(|c| !c.is_ascii_whitespace()),
+        "no JSON document found"
+    );
+    let included = settings.include_keys.is_empty();
+    if settings.fast {
+        // simd-json only parses single documents; the others, as well as the
+        // reporting of syntax errors, are left to serde_json. It also parses
+        // in place, and the input, possibly mapped read-only, is copied
+        let mut buffer = content.to_vec();
+        if let Ok(mut de) = simd_json::Deserializer::from_slice(&mut buffer) {
+            let seed = Seed {
+                ks: 0,
+                // Concatenated documents are actually one level deeper; this
+                // only keeps more nodes than needed
+                depth: 0,
+                crumb: None,
+                tag: tag.clone().into(),
+                settings,
+                included,
+            };
+            if let Ok(document) = seed.deserialize(&mut de) {
+                return Ok(document
+                    .node
+                    .unwrap_or_else(|| Node::object(tag, 0, Vec::new())));
+            }
+            // The failed pass may have gone partway through the input
+            settings.rewind();
+        }
+    }
+    let mut de = serde_json::Deserializer::from_slice(content);
+    // The limit then set by the user replaces that of serde_json
+    if settings.max_parse_depth.is_some() {
+        de.disable_recursion_limit();
+    }
+    // Documents are aggregated as they come, unless there is only one
+    let mut first = None;
+    let mut documents = Elements::default();
+    let mut count = 0;
+    loop {
+        let seed = Seed {
+            ks: 0,
+            depth: 0,
+            crumb: None,
+            tag: tag.clone().into(),
+            settings,
+            included,
+        };
+        let document = if settings.max_parse_depth.is_some() {
+            seed.deserialize(serde_stacker::Deserializer::new(&mut de))?
+        } else {
+            seed.deserialize(&mut de)?
+        };
+        if count == 0 {
+            first = Some(document);
+        } else {
+            if let Some(first) = first.take() {
+                aggregate(&mut documents, 0, first, settings);
+            }
+            aggregate(&mut documents, count, document, settings);
+        }
+        count += 1;
+        if de.end().is_ok() {
+            break;
+        }
+    }
+
+    let root = match first {
+        Some(document) => document.node,
+        None => (included || documents.len > 0)
+            .then(|| documents.into_node(tag.clone(), 0, 2 + count - 1, settings)),
+    };
+    Ok(root.unwrap_or_else(|| Node::object(tag, 0, Vec::new())))
+}
+
+/// Build the size tree of the NDJSON document in `content`, as an array of
+/// its non-empty lines.
+pub fn parse_lines(content: &[u8], settings: &ParseSettings, tag: String) -> Result<Node> {
+    let included = settings.include_keys.is_empty();
+    let mut documents = Elements::default();
+    let mut count = 0;
+    for (i, line) in content.split(|&c| c == b'\n').enumerate() {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let crumb = Crumb {
+            step: Step::Index(count),
+            parent: None,
+        };
+        let seed = Seed {
+            ks: 0,
+            depth: 1,
+            crumb: Some(&crumb),
+            tag: Tag::default(),
+            settings,
+            included,
+        };
+        let mut de = serde_json::Deserializer::from_slice(line);
+        let document = seed
+            .deserialize(&mut de)
+            .and_then(|document| de.end().map(|_| document))
+            .with_context(|| format!("while parsing line {}", i + 1))?;
+        aggregate(&mut documents, count, document, settings);
+        count += 1;
+    }
+    Ok(if included || documents.len > 0 {
+        documents.into_node(tag, 0, 2 + count.saturating_sub(1), settings)
+    } else {
+        Node::object(tag, 0, Vec::new())
+    })
+}
+
+/// Add the `i`-th document of a stream to the elements of its root.
+fn aggregate(documents: &mut Elements, i: usize, document: Walked, settings: &ParseSettings) {
+    if let Some(mut node) = document.node {
+        if settings.expand_arrays {
+            node.tag = Some(match document.id {
+                Some(id) => settings.tags.get(&id),
+                None => i.to_string().into(),
+            });
+        }
+        documents.push(node, settings);
+    }
+}
+
+/// A parsed value.
+struct Walked {
+    /// Its size tree, if it was not filtered out
+    node: Option<Node>,
+    /// Its text, if it is a scalar that may label an array element
+    text: Option<String>,
+    /// The text of its `--id-field` entry, if it is an object
+    id: Option<String>,
+}
+
+/// A step of the path to a value, linked to the step to its parent.
+struct Crumb<'a> {
+    step: Step<'a>,
+    parent: Option<&'a Crumb<'a>>,
+}
+
+enum Step<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+impl Crumb<'_> {
+    /// The JSON pointer to the value.
+    fn path(&self) -> String {
+        let mut steps = Vec::new();
+        let mut crumb = Some(self);
+        while let Some(c) = crumb {
+            steps.push(&c.step);
+            crumb = c.parent;
+        }
+        steps
+            .iter()
+            .rev()
+            .fold(String::new(), |path, step| match step {
+                Step::Key(k) => output::child_path(&path, k),
+                Step::Index(i) => output::child_path(&path, &i.to_string()),
+            })
+    }
+}
+
+/// Parse a value as [`Node::walk`] would walk it.
+struct Seed<'a> {
+    ks: usize,
+    depth: usize,
+    /// The last step of the path to the value, if it is not the root
+    crumb: Option<&'a Crumb<'a>>,
+    tag: Tag,
+    settings: &'a ParseSettings,
+    included: bool,
+}
+impl Seed<'_> {
+    /// Fail if the container about to be parsed nests deeper than allowed.
+    fn nest<E: Error>(&self) -> Result<(), E> {
+        match self.settings.max_parse_depth {
+            Some(max) if self.depth >= max => Err(E::custom(super::too_deep(
+                max,
+                &self.crumb.map(Crumb::path).unwrap_or_default(),
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn scalar(self, value: Value) -> Walked {
+        Walked {
+            text: self.settings.id_field.as_ref().and_then(|_| value.label()),
+            node: Node::walk(
+                &value,
+                self.ks,
+                self.depth,
+                self.tag,
+                self.settings,
+                self.included,
+            ),
+            id: None,
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Seed<'_> {
+    type Value = Walked;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Walked, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Seed<'_> {
+    type Value = Walked;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Walked, E> {
+        Ok(self.scalar(Value::Null))
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Walked, E> {
+        Ok(self.scalar(Value::Bool(v)))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Walked, E> {
+        Ok(self.scalar(Value::from(v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Walked, E> {
+        Ok(self.scalar(Value::from(v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Walked, E> {
+        Ok(self.scalar(Value::from(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Walked, E> {
+        Ok(self.scalar(Value::from(v)))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Walked, E> {
+        Ok(self.scalar(Value::from(v)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Walked, A::Error> {
+        self.nest()?;
+        let settings = self.settings;
+        let mut count: usize = 0;
+        let mut elements = Elements::default();
+        loop {
+            let crumb = Crumb {
+                step: Step::Index(count),
+                parent: self.crumb,
+            };
+            let tag = if settings.expand_arrays {
+                settings.tags.get(&count.to_string())
+            } else {
+                Tag::default()
+            };
+            let Some(element) = seq.next_element_seed(Seed {
+                ks: 0,
+                depth: self.depth + 1,
+                crumb: Some(&crumb),
+                tag,
+                settings,
+                included: self.included,
+            })?
+            else {
+                break;
+            };
+            count += 1;
+            if let Some(mut node) = element.node {
+                if let (true, Some(id)) = (settings.expand_arrays, element.id) {
+                    node.tag = Some(settings.tags.get(&id));
+                }
+                elements.push(node, settings);
+            }
+        }
+        let overhead = 2 + count.saturating_sub(1);
+        settings.advance(self.ks + overhead);
+        Ok(Walked {
+            node: (self.included || elements.len > 0).then(|| {
+                let mut node = elements.into_node(self.tag, self.ks, overhead, settings);
+                node.prune(self.depth, settings);
+                node
+            }),
+            text: None,
+            id: None,
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Walked, A::Error> {
+        self.nest()?;
+        let settings = self.settings;
+        let mut overhead = 2;
+        let mut count: usize = 0;
+        let mut id = None;
+        let (mut keys, mut children) = (Vec::new(), Vec::new());
+        while let Some(k) = map.next_key::<String>()? {
+            count += 1;
+            overhead += quoted_len(&k) - k.len() + 1;
+            let is_id = settings.id_field.as_ref() == Some(&k);
+            if settings.exclude_keys.iter().any(|r| r.is_match(&k)) && !is_id {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            }
+            let included = self.included || settings.include_keys.iter().any(|r| r.is_match(&k));
+            let crumb = Crumb {
+                step: Step::Key(&k),
+                parent: self.crumb,
+            };
+            let value = map.next_value_seed(Seed {
+                ks: k.len(),
+                depth: self.depth + 1,
+                crumb: Some(&crumb),
+                tag: settings.tags.get(&k),
+                settings,
+                included,
+            })?;
+            if is_id {
+                id = value.text;
+                // The id field was only parsed to label the object
+                if settings.exclude_keys.iter().any(|r| r.is_match(&k)) {
+                    continue;
+                }
+            }
+            if let Some(node) = value.node {
+                keys.push((k.len(), k.len()));
+                children.push(node);
+            }
+        }
+        overhead += count.saturating_sub(1);
+        settings.advance(self.ks + overhead);
+        Ok(Walked {
+            node: (self.included || !children.is_empty()).then(|| {
+                let mut node = Node::entries(self.tag, self.ks, overhead, &keys, children);
+                node.prune(self.depth, settings);
+                node
+            }),
+            text: None,
+            id,
+        })
+    }
+}
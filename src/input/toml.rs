@@ -0,0 +1,26 @@
+use anyhow::*;
+use serde_json::Value;
+
+pub fn parse(content: &str) -> Result<Value> {
+    Ok(from_toml(content.parse::<toml::Table>()?))
+}
+
+/// Datetimes have no JSON counterpart, and are represented by their RFC 3339
+/// string.
+fn from_toml(t: toml::Table) -> Value {
+    fn convert(v: toml::Value) -> Value {
+        match v {
+            toml::Value::String(s) => Value::String(s),
+            toml::Value::Integer(i) => Value::from(i),
+            toml::Value::Float(f) => serde_json::Number::from_f64(f)
+                .map(Value::Number)
+                .unwrap_or_else(|| Value::String(f.to_string())),
+            toml::Value::Boolean(b) => Value::Bool(b),
+            toml::Value::Datetime(d) => Value::String(d.to_string()),
+            toml::Value::Array(xs) => Value::Array(xs.into_iter().map(convert).collect()),
+            toml::Value::Table(t) => from_toml(t),
+        }
+    }
+
+    Value::Object(t.into_iter().map(|(k, v)| (k, convert(v))).collect())
+}
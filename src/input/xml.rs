@@ -0,0 +1,50 @@
+use anyhow::*;
+use serde_json::{Map, Value};
+
+pub fn parse(content: &str) -> Result<Value> {
+    let document = roxmltree::Document::parse(content)?;
+    let root = document.root_element();
+    let mut r = Map::new();
+    r.insert(root.tag_name().name().to_owned(), from_element(root));
+    Ok(Value::Object(r))
+}
+
+/// Elements are converted following the usual conventions: attributes are
+/// stored under `@`-prefixed keys, text content under `#text`, and repeated
+/// children are grouped in an array. Elements without attributes nor
+/// children are reduced to their text.
+fn from_element(e: roxmltree::Node) -> Value {
+    let text = e
+        .children()
+        .filter(|c| c.is_text())
+        .filter_map(|c| c.text())
+        .map(str::trim)
+        .collect::<String>();
+
+    if e.attributes().len() == 0 && !e.children().any(|c| c.is_element()) {
+        return Value::String(text);
+    }
+
+    let mut r = Map::new();
+    for a in e.attributes() {
+        r.insert(
+            format!("@{}", a.name()),
+            Value::String(a.value().to_owned()),
+        );
+    }
+    for c in e.children().filter(|c| c.is_element()) {
+        let name = c.tag_name().name();
+        let child = from_element(c);
+        match r.get_mut(name) {
+            Some(Value::Array(xs)) => xs.push(child),
+            Some(previous) => *previous = Value::Array(vec![previous.take(), child]),
+            None => {
+                r.insert(name.to_owned(), child);
+            }
+        }
+    }
+    if !text.is_empty() {
+        r.insert("#text".to_owned(), Value::String(text));
+    }
+    Value::Object(r)
+}
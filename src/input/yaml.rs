@@ -0,0 +1,50 @@
+use anyhow::*;
+use serde_json::Value;
+
+pub fn parse(content: &str) -> Result<Value> {
+    let mut yaml: serde_yaml::Value = serde_yaml::from_str(content)?;
+    yaml.apply_merge()?;
+    Ok(from_yaml(yaml))
+}
+
+fn yaml_key(k: serde_yaml::Value) -> String {
+    match k {
+        serde_yaml::Value::String(s) => s,
+        serde_yaml::Value::Null => "null".to_owned(),
+        serde_yaml::Value::Bool(b) => b.to_string(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        k => serde_yaml::to_string(&k)
+            .map(|s| s.trim_end().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+/// YAML is a superset of JSON: tags are dropped, and non-string keys are
+/// converted to their textual representation.
+fn from_yaml(v: serde_yaml::Value) -> Value {
+    match v {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                // NaN & infinities can not be represented in JSON
+                n.as_f64()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+                    .unwrap_or_else(|| Value::String(n.to_string()))
+            }
+        }
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(xs) => Value::Array(xs.into_iter().map(from_yaml).collect()),
+        serde_yaml::Value::Mapping(m) => Value::Object(
+            m.into_iter()
+                .map(|(k, v)| (yaml_key(k), from_yaml(v)))
+                .collect(),
+        ),
+        serde_yaml::Value::Tagged(t) => from_yaml(t.value),
+    }
+}
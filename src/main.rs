@@ -1,36 +1,142 @@
 use anyhow::*;
-use clap::{Parser, ValueEnum};
-use colored::{Color, Colorize};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use colored::Color;
 use human_format::*;
-use serde_json::Value;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufWriter, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use thousands::Separable;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
+mod cache;
+mod expr;
+mod grep;
+mod input;
+mod memory;
+mod output;
+mod snapshot;
+mod theme;
+mod tokens;
+mod tui;
+
+use expr::Expr;
+use input::{Document, InputFormat, Kind, ParseSettings, Shape};
+use output::OutputFormat;
+use tokens::Tokenizer;
+
+/// The share of the weight of its parent above which a single child is
+/// merged with it when collapsing chains.
+const CHAIN_RATIO: f64 = 0.99;
+/// The number of orders of magnitude spanned by logarithmic bars.
+const LOG_DECADES: f32 = 6.;
+/// How often to check watched files for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// How often to re-render the report of a followed file.
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
 enum Unit {
+    /// the size of the values in the document encoding, excluding keys
     Bytes,
+    /// the number of descendants
     Children,
+    /// the size of the subtrees in the document encoding, including keys and syntax
+    Serialized,
+    /// the estimated memory footprint once deserialized
+    Memory,
+    /// the number of scalars
+    Leaves,
+    /// the size of the keys
+    Keys,
+    /// the number of characters of the values, counting strings in Unicode code points
+    Chars,
+    /// the estimated size of the subtrees once serialized and gzipped
+    Gzip,
+    /// the number of language model tokens of the subtrees once serialized
+    Tokens,
+    /// the number of distinct scalar values
+    Distinct,
+    /// the value of the --weight-expr expression
+    Weight,
 }
 impl Unit {
-    fn format(&self, x: usize) -> String {
-        match self {
-            Unit::Bytes => Formatter::new()
+    fn name(&self) -> String {
+        self.to_possible_value()
+            .map(|v| v.get_name().to_owned())
+            .unwrap_or_default()
+    }
+
+    /// Whether the nodes weigh at least as much as their children in this
+    /// unit, so that a node too light to be displayed only has descendants
+    /// too light as well.
+    fn additive(&self) -> bool {
+        !matches!(
+            self,
+            Unit::Gzip | Unit::Tokens | Unit::Distinct | Unit::Weight
+        )
+    }
+
+    /// Format `x` in this unit, either rounded or `exact`.
+    fn format(&self, x: usize, exact: bool) -> String {
+        let bytes = matches!(
+            self,
+            Unit::Bytes | Unit::Serialized | Unit::Memory | Unit::Keys | Unit::Gzip
+        );
+        match (bytes, exact) {
+            (true, true) => format!("{} B", x.separate_with_commas()),
+            (false, true) => x.separate_with_commas(),
+            (true, false) => Formatter::new()
                 .with_scales(Scales::Binary())
                 .with_suffix("B")
                 .format(x as f64),
-            Unit::Children => Formatter::new().with_scales(Scales::SI()).format(x as f64),
+            (false, false) => Formatter::new().with_scales(Scales::SI()).format(x as f64),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum SortOrder {
+    /// the heaviest first
+    Size,
+    /// the lightest first
+    SizeAsc,
+    /// alphabetically by key
+    Name,
+    /// as in the document
+    Input,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum BarScale {
+    /// bars proportional to the size
+    Linear,
+    /// bars proportional to the order of magnitude of the size
+    Log,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
 enum Colorizer {
     Hellscape,
     Gradient,
     Monochrome,
+    Viridis,
+    Cividis,
+    /// by type rather than by size: objects in blue, arrays in cyan, strings
+    /// in green, numbers in yellow, booleans in magenta, and nulls in gray
+    Type,
     None,
 }
 impl Colorizer {
-    fn colorize(&self, rel: f32) -> Color {
+    fn colorize(&self, node: &Node, rel: f32) -> Color {
         match self {
             Colorizer::Hellscape => {
                 let rel_b = (155_f32 * rel) as u8;
@@ -56,22 +162,630 @@ impl Colorizer {
                     b: 100 + rel_b,
                 }
             }
+            Colorizer::Viridis => interpolate(VIRIDIS, rel),
+            Colorizer::Cividis => interpolate(CIVIDIS, rel),
+            Colorizer::Type => {
+                let (r, g, b) = match node.kind {
+                    Kind::Object => (90, 140, 255),
+                    Kind::Array => (80, 200, 220),
+                    Kind::String => (110, 200, 110),
+                    Kind::Number => (220, 200, 90),
+                    Kind::Bool => (200, 120, 220),
+                    Kind::Null => (140, 140, 140),
+                    Kind::Binary => (230, 120, 90),
+                    Kind::Other => (220, 220, 220),
+                };
+                Color::TrueColor { r, g, b }
+            }
             Colorizer::None => Color::White,
         }
     }
 }
 
+/// Samples of the viridis and cividis colormaps, perceptually uniform and
+/// readable with color-vision deficiencies.
+const VIRIDIS: &[(u8, u8, u8)] = &[
+    (0x44, 0x01, 0x54),
+    (0x47, 0x2d, 0x7b),
+    (0x3b, 0x52, 0x8b),
+    (0x2c, 0x72, 0x8e),
+    (0x21, 0x91, 0x8c),
+    (0x28, 0xae, 0x80),
+    (0x5e, 0xc9, 0x62),
+    (0xad, 0xdc, 0x30),
+    (0xfd, 0xe7, 0x25),
+];
+const CIVIDIS: &[(u8, u8, u8)] = &[
+    (0x00, 0x22, 0x4e),
+    (0x12, 0x35, 0x70),
+    (0x3b, 0x49, 0x6c),
+    (0x57, 0x5d, 0x6d),
+    (0x70, 0x71, 0x73),
+    (0x8a, 0x87, 0x79),
+    (0xa6, 0x9d, 0x75),
+    (0xc4, 0xb5, 0x6c),
+    (0xfe, 0xe8, 0x38),
+];
+
+/// The color at `rel` of a gradient through `colors`, evenly spread.
+fn interpolate(colors: &[(u8, u8, u8)], rel: f32) -> Color {
+    let x = rel.clamp(0., 1.) * (colors.len() - 1) as f32;
+    let i = (x as usize).min(colors.len() - 2);
+    let t = x - i as f32;
+    let (from, to) = (colors[i], colors[i + 1]);
+    let mix = |a: u8, b: u8| (a as f32 + t * (b as f32 - a as f32)).round() as u8;
+    Color::TrueColor {
+        r: mix(from.0, to.0),
+        g: mix(from.1, to.1),
+        b: mix(from.2, to.2),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum BarStyle {
+    /// bars drawn with the bar character
+    Chars,
+    /// bars drawn as a colored background over a dimmed track, when colors
+    /// are enabled
+    Background,
+}
+
+/// The background of the unfilled part of background bars.
+const BAR_TRACK: Color = Color::TrueColor {
+    r: 48,
+    g: 48,
+    b: 48,
+};
+
+/// When to colorize the reports.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum ColorWhen {
+    /// unless NO_COLOR is set or the output is not a terminal
+    Auto,
+    /// even if the output is not a terminal
+    Always,
+    /// even if the output is a terminal
+    Never,
+}
+
+/// The colors a terminal can display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Palette {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+impl Palette {
+    /// Guess the palette of the terminal from its environment.
+    fn detect() -> Palette {
+        let var = |k: &str| std::env::var(k).unwrap_or_default();
+        if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+            Palette::TrueColor
+        } else if var("TERM").contains("256color") {
+            Palette::Ansi256
+        } else {
+            Palette::Ansi16
+        }
+    }
+
+    /// The closest color to `color` in this palette.
+    fn quantize(&self, color: Color) -> Color {
+        match (self, color) {
+            (Palette::Ansi256, Color::TrueColor { r, g, b }) => Color::AnsiColor(ansi256(r, g, b)),
+            (Palette::Ansi16, Color::TrueColor { r, g, b }) => ansi16(r, g, b),
+            (_, color) => color,
+        }
+    }
+}
+
+/// The ANSI color of the same hue, as the closest one by distance would
+/// turn most of the desaturated gradients gray.
+fn ansi16(r: u8, g: u8, b: u8) -> Color {
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if max - min < 32 {
+        return match max {
+            0..=63 => Color::Black,
+            64..=159 => Color::BrightBlack,
+            160..=223 => Color::White,
+            _ => Color::BrightWhite,
+        };
+    }
+    let mid = ((max as u16 + min as u16) / 2) as u8;
+    let bright = max > 191;
+    match (r > mid, g > mid, b > mid, bright) {
+        (true, false, false, false) => Color::Red,
+        (true, false, false, true) => Color::BrightRed,
+        (false, true, false, false) => Color::Green,
+        (false, true, false, true) => Color::BrightGreen,
+        (true, true, false, false) => Color::Yellow,
+        (true, true, false, true) => Color::BrightYellow,
+        (false, false, true, false) => Color::Blue,
+        (false, false, true, true) => Color::BrightBlue,
+        (true, false, true, false) => Color::Magenta,
+        (true, false, true, true) => Color::BrightMagenta,
+        (false, true, true, false) => Color::Cyan,
+        (false, true, true, true) => Color::BrightCyan,
+        (_, _, _, false) => Color::White,
+        (_, _, _, true) => Color::BrightWhite,
+    }
+}
+
+/// The closest color of the 256-color palette, either in its 6×6×6 cube or
+/// in its grayscale ramp.
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| {
+        if v < 48 {
+            0
+        } else if v < 115 {
+            1
+        } else {
+            (v - 35) / 40
+        }
+    };
+    let value = |l: u8| if l == 0 { 0 } else { 55 + 40 * l };
+    let (lr, lg, lb) = (level(r), level(g), level(b));
+    let gray = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let step = (gray.saturating_sub(8) / 10).min(23);
+    let distance = |(x, y, z): (u8, u8, u8)| {
+        [(r, x), (g, y), (b, z)]
+            .iter()
+            .map(|&(a, b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+    let level_gray = 8 + 10 * step;
+    if distance((level_gray, level_gray, level_gray)) < distance((value(lr), value(lg), value(lb)))
+    {
+        232 + step
+    } else {
+        16 + 36 * lr + 6 * lg + lb
+    }
+}
+
+/// A gradient through colors evenly spread from the smallest to the largest
+/// nodes.
+#[derive(Debug, Clone)]
+struct Gradient(Vec<(u8, u8, u8)>);
+impl std::str::FromStr for Gradient {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Gradient> {
+        let colors = s.split(':').map(parse_color).collect::<Result<Vec<_>>>()?;
+        ensure!(colors.len() >= 2, "a gradient requires at least two colors");
+        Ok(Gradient(colors))
+    }
+}
+
+/// Discrete colors for the nodes weighting at least some percentages of the
+/// total size, sorted by increasing percentage.
+#[derive(Debug, Clone)]
+struct Heat(Vec<(f32, (u8, u8, u8))>);
+impl Heat {
+    fn at(&self, rel: f32) -> Color {
+        self.0
+            .iter()
+            .rev()
+            .find(|(percent, _)| 100. * rel >= *percent)
+            .map_or(Color::White, |(_, (r, g, b))| Color::TrueColor {
+                r: *r,
+                g: *g,
+                b: *b,
+            })
+    }
+}
+impl std::str::FromStr for Heat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Heat> {
+        let mut buckets = s
+            .split(',')
+            .map(|bucket| {
+                let (percent, color) = bucket
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("`{}` is not a PERCENT:COLOR bucket", bucket))?;
+                let percent = percent
+                    .trim()
+                    .trim_end_matches('%')
+                    .parse::<f32>()
+                    .with_context(|| format!("invalid percentage `{}`", percent))?;
+                Ok((percent, parse_color(color)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        buckets.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Heat(buckets))
+    }
+}
+
+/// Parse a color given by its name or as `#rrggbb` or `#rgb`.
+fn parse_color(s: &str) -> Result<(u8, u8, u8)> {
+    let s = s.trim();
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "black" => (0, 0, 0),
+        "red" => (220, 50, 47),
+        "orange" => (240, 140, 40),
+        "yellow" => (230, 200, 60),
+        "green" => (80, 200, 80),
+        "cyan" => (60, 200, 210),
+        "blue" => (60, 120, 230),
+        "magenta" => (200, 80, 200),
+        "gray" | "grey" => (140, 140, 140),
+        "white" => (230, 230, 230),
+        _ => {
+            let hex = s.trim_start_matches('#');
+            let hex = if hex.len() == 3 {
+                hex.chars().flat_map(|c| [c, c]).collect()
+            } else {
+                hex.to_owned()
+            };
+            ensure!(
+                hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+                "`{}` is neither a color name nor a #rrggbb color",
+                s
+            );
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+            (channel(0), channel(2), channel(4))
+        }
+    })
+}
+
 struct DisplaySettings {
     counter: Unit,
+    /// Additional units to display the sizes in
+    columns: Vec<Unit>,
     colorizer: Colorizer,
+    /// The colors of the gradient colorizer, if not the default ones
+    gradient: Option<Gradient>,
+    /// The discrete colors to use instead of the colorizer
+    heat: Option<Heat>,
+    /// The colors the terminal can display
+    palette: Palette,
     depth: Option<usize>,
+    /// The number of top levels not to print, although they are recursed into
+    min_depth: usize,
+    threshold: f32,
+    /// The size under which nodes are hidden, in the main unit
+    min_size: usize,
     width: usize,
+    ascii: bool,
+    /// Whether to show the depth of the subtrees
+    show_depth: bool,
+    /// Whether to show the type of the nodes
+    show_types: bool,
+    /// Whether to highlight the smallest nodes rather than the largest
+    invert: bool,
+    /// Whether to label nodes with their JSON pointer rather than their key
+    show_paths: bool,
+    /// The character the bars are drawn with
+    bar_char: char,
+    /// The width of the bars, if not a third of the available space
+    bar_width: Option<usize>,
+    bar_scale: BarScale,
+    bar_style: BarStyle,
+    /// Whether to print sizes in full rather than rounded
+    exact: bool,
+    /// Whether to also show sizes as a percentage of the parent size
+    parent_percent: bool,
+    /// Whether to draw guide lines between nodes and their children
+    tree: bool,
+    /// How many characters of the scalar values to preview
+    preview: Option<usize>,
+    /// Whether to print a summary of the whole document after the tree
+    summary: bool,
+    /// Whether to print a line labelling the columns of the table
+    header: bool,
+    /// How many rows of the table to print at most
+    max_lines: Option<usize>,
+    /// Whether to wrap the taglines that are too long rather than truncate
+    /// them
+    wrap: bool,
+}
+impl DisplaySettings {
+    /// Whether a node at the given depth should be displayed.
+    fn shows(&self, node: &Node, depth: usize, total_size: usize) -> bool {
+        self.shows_size(node.size(self.counter), depth, total_size)
+    }
+
+    /// Whether a node of the given size and depth should be displayed.
+    fn shows_size(&self, size: usize, depth: usize, total_size: usize) -> bool {
+        self.depth.is_none_or(|max_depth| depth < max_depth)
+            && size as f32 >= self.threshold * total_size as f32
+            && size >= self.min_size
+    }
+
+    /// The widths of the tagline and of the bar of a line `width` columns
+    /// wide, `w_numbers` of which are taken by the numbers.
+    fn layout(&self, width: usize, w_numbers: usize) -> (usize, usize) {
+        match self.bar_width {
+            Some(w_bar) => {
+                let free = width.saturating_sub(w_numbers + 2);
+                let w_bar = w_bar.min(free);
+                (free - w_bar, w_bar)
+            }
+            None => {
+                let w_tagline = (width.saturating_sub(w_numbers) * 2) / 3;
+                (w_tagline, width.saturating_sub(w_numbers + w_tagline + 2))
+            }
+        }
+    }
+
+    /// Pad `s` to exactly `width` terminal columns, truncating it on a
+    /// grapheme boundary with an ellipsis if it does not fit.
+    fn fit(&self, s: &str, width: usize) -> String {
+        let mut fitted = String::new();
+        let mut w = s.width();
+        if w > width {
+            let ellipsis = if self.ascii { "..." } else { "…" };
+            let max = width.saturating_sub(ellipsis.width() + 1);
+            w = 0;
+            for g in s.graphemes(true) {
+                if w + g.width() > max {
+                    break;
+                }
+                fitted.push_str(g);
+                w += g.width();
+            }
+            fitted.push_str(ellipsis);
+            w += ellipsis.width();
+        } else {
+            fitted.push_str(s);
+        }
+        fitted.push_str(&" ".repeat(width.saturating_sub(w)));
+        fitted
+    }
+
+    /// The bar of a node weighting `rel` of the total size.
+    fn bar(&self, rel: f32, w_bar: usize) -> String {
+        self.bar_char
+            .to_string()
+            .repeat(self.bar_length(rel, w_bar))
+    }
+
+    /// The length of the bar of a node weighting `rel` of the total size.
+    fn bar_length(&self, rel: f32, w_bar: usize) -> usize {
+        let length = match self.bar_scale {
+            BarScale::Linear => rel,
+            BarScale::Log if rel > 0. => (1. + rel.log10() / LOG_DECADES).max(0.),
+            BarScale::Log => 0.,
+        };
+        (length * w_bar as f32) as usize
+    }
+
+    /// The color of `node`, weighting `rel` of the total size.
+    fn color(&self, node: &Node, rel: f32) -> Color {
+        if let Some(heat) = &self.heat {
+            return heat.at(rel);
+        }
+        let rel = if self.invert { 1. - rel } else { rel };
+        match &self.gradient {
+            Some(gradient) if self.colorizer == Colorizer::Gradient => {
+                interpolate(&gradient.0, rel)
+            }
+            _ => self.colorizer.colorize(node, rel),
+        }
+    }
+
+    /// The color of `node`, weighting `rel` of the total size, as displayed
+    /// by the terminal.
+    fn term_color(&self, node: &Node, rel: f32) -> Color {
+        self.palette.quantize(self.color(node, rel))
+    }
+
+    /// Whether a displayed node at the given depth should be printed in
+    /// line-oriented reports.
+    fn prints(&self, depth: usize) -> bool {
+        depth >= self.min_depth
+    }
+}
+
+/// Parse a size such as `300`, `10KB`, `1.5M` or `2GiB`; SI suffixes are
+/// powers of 1000, and IEC ones powers of 1024.
+fn parse_size(s: &str) -> Result<usize> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, suffix) = s.split_at(split);
+    let number = number
+        .parse::<f64>()
+        .with_context(|| format!("invalid size `{}`", s))?;
+    let suffix = suffix.trim().to_ascii_lowercase();
+    let suffix = suffix.strip_suffix('b').unwrap_or(&suffix);
+    let (prefix, base) = match suffix.strip_suffix('i') {
+        Some(prefix) => (prefix, 1024_f64),
+        None => (suffix, 1000_f64),
+    };
+    let exponent = match prefix {
+        "" if base == 1000. => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        "p" => 5,
+        _ => bail!("invalid size suffix in `{}`", s),
+    };
+    Ok((number * base.powi(exponent)).round() as usize)
 }
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "explore the documents interactively")]
+    Tui(Args),
+    #[command(about = "locate the keys and values matching a regex, with their size")]
+    Grep {
+        #[arg(help = "the regex to search for in keys and scalar values")]
+        pattern: Regex,
+        #[command(flatten)]
+        args: Args,
+    },
+    #[command(
+        about = "compare the size of two versions of the documents, node by node: the first file and the others, or the files and their committed version"
+    )]
+    Diff {
+        #[arg(
+            long,
+            value_name = "REVISION",
+            help = "compare the files with their version at this git revision, e.g. HEAD~5"
+        )]
+        git: Option<String>,
+        #[command(flatten)]
+        args: Args,
+    },
+    #[command(
+        about = "record the analysis of the documents, to follow their growth with `history`"
+    )]
+    Snapshot(Args),
+    #[command(about = "show how the size of a node changed across the snapshots of the documents")]
+    History {
+        #[arg(
+            help = "the JSON pointer of the node to follow, e.g. /data/items; `/` for the whole document"
+        )]
+        pointer: String,
+        #[command(flatten)]
+        args: Args,
+    },
+    #[command(about = "measure the time and memory taken to analyze the documents")]
+    Bench {
+        #[arg(
+            long,
+            default_value_t = 1,
+            help = "how many times to analyze the documents; the fastest times are reported"
+        )]
+        runs: usize,
+        #[command(flatten)]
+        args: Args,
+    },
+}
+
+/// What the subcommands do instead of reporting the analysis.
+enum Task {
+    /// Compare with another version of the documents, at this git revision if
+    /// any
+    Diff(Option<String>),
+    Snapshot,
+    /// Follow the node at this pointer across snapshots
+    History(String),
+    /// Benchmark this many runs
+    Bench(usize),
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
-    json_file: String,
+    #[arg(help = "the files, globs, or HTTP(S) URLs to analyze; `-` or none to read from stdin")]
+    files: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "analyze all the supported files found in directories"
+    )]
+    recursive: bool,
+
+    #[arg(
+        short = 'H',
+        long = "header",
+        value_name = "HEADER",
+        help = "an HTTP header to send when fetching an URL, as `Name: value`; can be repeated"
+    )]
+    headers: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        value_enum,
+        help = "the format of the input file; guessed from its extension if not set"
+    )]
+    input_format: Option<InputFormat>,
+
+    #[arg(
+        long,
+        help = "accept comments, trailing commas and other JSON5 extensions in JSON inputs; implied by the .json5 & .jsonc extensions"
+    )]
+    relaxed: bool,
+
+    #[arg(
+        long,
+        help = "parse JSON inputs, but not NDJSON ones, with SIMD instructions; faster on large documents, but holds a copy of them in memory"
+    )]
+    fast: bool,
+
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        help = "fail on documents nesting values deeper than this; JSON documents may then nest deeper than the default limit of 128 levels"
+    )]
+    max_parse_depth: Option<usize>,
+
+    #[arg(
+        long,
+        help = "keep the size trees of local files in ~/.cache/toison, to render them again without parsing them"
+    )]
+    cache: bool,
+
+    #[arg(
+        long,
+        value_name = "POINTER",
+        help = "only analyze the subtree at this JSON pointer, e.g. /data/items/3"
+    )]
+    root: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PROGRAM",
+        help = "transform the documents with a jq program before analysis"
+    )]
+    filter: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "JSONPATH",
+        help = "only analyze the nodes matching a JSONPath, e.g. `$.store.book[*].reviews`"
+    )]
+    select: Option<String>,
+
+    #[arg(
+        long = "include-key",
+        value_name = "REGEX",
+        help = "only analyze the entries whose key, or a descendant key, matches this regex; may be repeated"
+    )]
+    include_keys: Vec<Regex>,
+
+    #[arg(
+        long = "exclude-key",
+        value_name = "REGEX",
+        help = "ignore the entries whose key matches this regex; may be repeated"
+    )]
+    exclude_keys: Vec<Regex>,
+
+    #[arg(long, help = "weight Parquet columns by their uncompressed size")]
+    uncompressed: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "the protobuf descriptor set describing the message to decode"
+    )]
+    proto_descriptor: Option<String>,
+
+    #[arg(
+        long,
+        help = "the fully-qualified name of the protobuf message to decode"
+    )]
+    message_type: Option<String>,
 
     #[arg(
         short,
@@ -81,6 +795,15 @@ struct Args {
     )]
     threshold: f32,
 
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        default_value = "0",
+        help = "hide nodes under this size in the main unit, e.g. 10KB or 2MiB"
+    )]
+    min_size: usize,
+
     #[arg(
         short,
         long,
@@ -89,169 +812,1379 @@ struct Args {
     )]
     max_depth: Option<isize>,
 
-    #[arg(short, long, value_enum, default_value_t = Unit::Bytes, help="the unit with which to weight nodes")]
-    unit: Unit,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "only render the N heaviest children of each node, summarizing the others"
+    )]
+    top: Option<usize>,
 
-    #[arg(short, long, value_enum, default_value_t = Colorizer::Hellscape, help="how to colorize output")]
-    colors: Colorizer,
-}
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "only render the heaviest children of each node covering this percentage of its size, summarizing the others"
+    )]
+    coverage: Option<f32>,
 
-#[derive(Debug, Clone)]
-struct Node {
-    tag: Option<String>,
-    len: usize,
-    size_b: usize,
-    size_c: usize,
-    key_size: usize,
-    children: Option<Vec<Node>>,
-}
-impl Node {
-    fn from_json(n: &Value, ks: usize, tag: String) -> Node {
-        match n {
-            Value::Null => Node::leaf(ks, 0, tag),
-            Value::Bool(_) => Node::leaf(ks, 4, tag),
-            Value::Number(x) => Node::leaf(ks, x.to_string().len(), tag),
-            Value::String(s) => Node::leaf(ks, s.len(), tag),
-            Value::Array(children) => {
-                let children = children
-                    .iter()
-                    .map(|c| Node::from_json(c, 0, String::new()))
-                    .collect::<Vec<_>>();
-                Node {
-                    tag: Some(tag),
-                    len: children.len(),
-                    size_b: children.iter().map(|c| c.size_b).sum::<usize>(),
-                    size_c: children.len() + children.iter().map(|c| c.size_c).sum::<usize>(),
-                    key_size: children.iter().map(|c| c.key_size).sum::<usize>(),
-                    children: None,
-                }
-            }
-            Value::Object(_children) => {
-                let children = _children
-                    .iter()
-                    .map(|(k, v)| Node::from_json(v, k.len(), k.clone()))
-                    .collect::<Vec<_>>();
-                Node {
-                    tag: Some(tag),
-                    len: 0,
-                    size_b: children.iter().map(|c| c.size_b).sum::<usize>(),
-                    size_c: children.len() + children.iter().map(|c| c.size_c).sum::<usize>(),
-                    key_size: _children.keys().map(|k| k.len()).sum::<usize>(),
-                    children: Some(children),
-                }
-            }
-        }
-    }
+    #[arg(long, help = "only show objects and arrays, hiding scalars")]
+    containers_only: bool,
 
-    fn leaf(key_size: usize, size: usize, tag: String) -> Node {
-        Node {
-            tag: if tag.is_empty() { None } else { Some(tag) },
-            len: 0,
-            size_b: size,
-            size_c: 0,
-            key_size,
-            children: None,
-        }
-    }
+    #[arg(
+        long,
+        value_name = "N",
+        help = "only show the arrays of at least N elements, and their ancestors"
+    )]
+    min_len: Option<usize>,
 
-    fn render(&self, total_size: usize, depth: usize, threshold: f32, settings: &DisplaySettings) {
-        if let Some(max_depth) = settings.depth {
-            if depth >= max_depth {
-                return;
-            }
-        }
-        // 11 + 6 + 2 = 19 chars required for numbers
-        // -> (WIDTH - 19)×2/3 for tagline
-        // -> (WIDTH - 19)×1/3 for bar
-        let w_tagline = ((settings.width - 19) * 2) / 3;
-        let w_bar = settings.width - 19 - w_tagline - 2;
+    #[arg(
+        long,
+        help = "keep the elements of arrays in the tree, labelled by their index"
+    )]
+    expand_arrays: bool,
 
-        let rel_size = self.size(settings.counter) as f32 / total_size as f32;
-        if rel_size < threshold {
-            return;
-        }
+    #[arg(
+        long,
+        value_name = "K",
+        requires = "expand_arrays",
+        help = "only render the K heaviest elements of expanded arrays, summarizing the others"
+    )]
+    array_top: Option<usize>,
 
-        let indent = " ".repeat(2 * depth);
-        let cardinality = if self.len > 0 {
-            format!("[{}] ", self.len.to_string().separate_with_commas())
-        } else {
-            String::new()
-        };
-        let mut id = format!(
-            "{}{}{}",
-            indent,
-            cardinality,
-            &self.tag.clone().unwrap_or_default()
-        );
-        if id.len() > w_tagline {
-            id = format!("{}…", id.chars().take(w_tagline - 2).collect::<String>());
-        }
+    #[arg(
+        long,
+        value_name = "FIELD",
+        requires = "expand_arrays",
+        help = "label the object elements of expanded arrays by the value of this field rather than their index"
+    )]
+    id_field: Option<String>,
 
-        let header = format!(
-            "{:0w_tagline$} {:>6.2}% {:>11}",
-            id,
-            100. * rel_size,
-            format!("({})", settings.counter.format(self.size(settings.counter))),
-            w_tagline = w_tagline,
-        );
-        println!(
-            "{:55} {}",
-            header.color(settings.colorizer.colorize(rel_size)),
-            "▒".repeat((rel_size * w_bar as f32) as usize)
-        );
-        if let Some(children) = &self.children {
-            for child in children {
-                child.render(total_size, depth + 1, threshold, &settings);
-            }
-        }
-    }
+    #[arg(
+        long,
+        conflicts_with = "expand_arrays",
+        help = "aggregate the fields of the elements of arrays of objects in a synthetic [*] child"
+    )]
+    element_schema: bool,
 
-    fn size(&self, count: Unit) -> usize {
-        match count {
-            Unit::Bytes => self.size_b,
-            Unit::Children => self.size_c,
-        }
-    }
+    #[arg(
+        long,
+        value_name = "N",
+        help = "only keep the first N elements of every array as children or in schemas; the others are weighed but not kept, bounding the memory used by large arrays"
+    )]
+    sample: Option<usize>,
 
-    fn max_depth(&self) -> usize {
-        fn _max_depth(n: &Node, ax: usize) -> usize {
-            match n.children {
-                Some(ref children) => {
-                    1 + children
-                        .iter()
-                        .map(|c| _max_depth(c, ax))
-                        .max()
-                        .unwrap_or(0)
-                }
-                None => ax,
-            }
-        }
+    #[arg(
+        long,
+        help = "merge the nodes having a single child carrying nearly all their weight into a single line"
+    )]
+    collapse_chains: bool,
 
-        _max_depth(self, 0)
-    }
-}
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        help = "do not print the first N levels, starting the report at depth N"
+    )]
+    min_depth: usize,
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-
-    let root = Node::from_json(
-        &serde_json::from_str(
-            &std::fs::read_to_string(&args.json_file)
-                .with_context(|| format!("while reading `{}`", args.json_file))?,
-        )?,
-        0,
-        "Root".to_owned(),
-    );
+    #[arg(long, value_enum, default_value_t = SortOrder::Input, help = "how to order the children of each node")]
+    sort: SortOrder,
 
-    let width = if let Some((w, _)) = term_size::dimensions() {
-        w
-    } else {
-        100
-    };
+    #[arg(
+        long,
+        conflicts_with = "sort",
+        help = "sort and color nodes by smallness, to find unexpectedly small branches"
+    )]
+    invert: bool,
 
-    let settings = DisplaySettings {
-        counter: args.unit,
-        colorizer: args.colors,
+    #[arg(
+        short,
+        long = "unit",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Unit::Bytes],
+        help = "the unit with which to weight nodes; further comma-separated units are displayed in additional columns"
+    )]
+    units: Vec<Unit>,
+
+    #[arg(long, value_enum, default_value_t = Tokenizer::Cl100k, help = "the tokenizer used by the tokens unit")]
+    tokenizer: Tokenizer,
+
+    #[arg(
+        long,
+        value_name = "EXPR",
+        help = "weight nodes by an arithmetic expression of units, e.g. `bytes + 8*children`"
+    )]
+    weight_expr: Option<Expr>,
+
+    #[arg(short, long, value_enum, default_value_t = Colorizer::Hellscape, help="how to colorize output")]
+    colors: Colorizer,
+
+    #[arg(
+        long,
+        value_name = "COLORS",
+        help = "the colors of the gradient colorizer, from the smallest to the largest nodes, as `COLOR:COLOR[:...]`, each a name or #rrggbb"
+    )]
+    gradient: Option<Gradient>,
+
+    #[arg(
+        long,
+        value_name = "BUCKETS",
+        help = "color the nodes weighting at least given percentages of the total size with discrete colors rather than with --colors, as `PERCENT:COLOR,...`, e.g. `1:green,10:yellow,25:red`"
+    )]
+    heat: Option<Heat>,
+
+    #[arg(
+        long,
+        value_name = "THEME",
+        help = "a TOML file, or the name of one in ~/.config/toison/themes, setting the appearance options not set on the command line"
+    )]
+    theme: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = ColorWhen::Auto,
+        help = "when to use colors"
+    )]
+    color: ColorWhen,
+
+    #[arg(
+        long,
+        help = "only use ASCII characters and no colors, for logs and legacy terminals"
+    )]
+    ascii: bool,
+
+    #[arg(
+        long,
+        help = "show the maximum depth of each subtree, to spot deeply nested structures"
+    )]
+    show_depth: bool,
+
+    #[arg(
+        long,
+        help = "show the type of each node, i.e. {}, [], str, num, bool or null"
+    )]
+    show_types: bool,
+
+    #[arg(
+        long,
+        help = "label nodes with their full JSON pointer rather than their key"
+    )]
+    show_paths: bool,
+
+    #[arg(
+        long,
+        value_name = "CHAR",
+        help = "the character to draw the bars with"
+    )]
+    bar_char: Option<char>,
+
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        help = "the width of the bars; defaults to a third of the space left by the numbers"
+    )]
+    bar_width: Option<usize>,
+
+    #[arg(long, value_enum, default_value_t = BarScale::Linear, help = "how the bars length scales with the size")]
+    bar_scale: BarScale,
+
+    #[arg(long, value_enum, default_value_t = BarStyle::Chars, help = "how the bars are drawn")]
+    bar_style: BarStyle,
+
+    #[arg(long, help = "print sizes in full rather than rounded")]
+    exact: bool,
+
+    #[arg(
+        long,
+        help = "also show the size of nodes as a percentage of their parent"
+    )]
+    parent_percent: bool,
+
+    #[arg(long, help = "draw guide lines between nodes and their children")]
+    tree: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "append the first N characters of scalar values to their line"
+    )]
+    preview: Option<usize>,
+
+    #[arg(
+        long,
+        help = "print the total sizes, node count, leaf count and depth of the document after the tree"
+    )]
+    summary: bool,
+
+    #[arg(
+        long,
+        help = "do not print the line labelling the columns of the table"
+    )]
+    no_header: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "stop printing the table after N rows, and tell how many were omitted"
+    )]
+    max_lines: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        help = "the width of the reports, by default that of the terminal or $COLUMNS"
+    )]
+    width: Option<usize>,
+
+    #[arg(
+        long,
+        help = "wrap the keys and paths too long for the table on continuation lines rather than truncating them"
+    )]
+    wrap: bool,
+
+    /// The pattern searched by the grep subcommand
+    #[arg(skip)]
+    grep: Option<Regex>,
+
+    /// Whether the whole size tree must be built, e.g. to be explored
+    #[arg(skip)]
+    complete: bool,
+
+    /// The progress bars of the large inputs being parsed
+    #[arg(skip)]
+    progress: MultiProgress,
+
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table, help="the format of the report")]
+    format: OutputFormat,
+
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        help = "write the report to this file rather than to stdout; colors are disabled if it is not a terminal"
+    )]
+    output: Option<String>,
+
+    #[arg(
+        long,
+        help = "never pipe the report through $PAGER, even if it does not fit in the terminal"
+    )]
+    no_pager: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "re-analyze and re-render the report whenever the input files change"
+    )]
+    watch: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "watch",
+        help = "tail an NDJSON file, aggregating the fields of its lines as they are appended"
+    )]
+    follow: bool,
+}
+impl Args {
+    /// Whether `unit` has to be computed, either to be displayed or to
+    /// evaluate the weight expression.
+    fn uses(&self, unit: Unit) -> bool {
+        self.units.contains(&unit) || self.weight_expr.as_ref().is_some_and(|e| e.uses(unit))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    tag: Option<Tag>,
+    kind: Kind,
+    len: usize,
+    size_b: usize,
+    size_c: usize,
+    /// The size of the subtree once serialized, including the syntax of the
+    /// document encoding
+    size_s: usize,
+    /// The estimated memory footprint of the subtree once deserialized
+    size_m: usize,
+    /// The number of scalars in the subtree
+    size_l: usize,
+    /// The number of characters of the values in the subtree
+    size_u: usize,
+    /// The estimated size of the subtree once serialized and compressed
+    size_z: usize,
+    /// The number of tokens of the subtree once serialized
+    size_t: usize,
+    /// The value of the user-defined weight expression on the subtree
+    size_w: usize,
+    /// The number of distinct scalar values in the subtree
+    size_d: usize,
+    /// The hashes of the distinct scalar values in the subtree, only kept
+    /// when they are counted
+    values: Option<Values>,
+    /// Whether the value of a scalar matches the searched pattern
+    matched: bool,
+    /// The beginning of the value of a scalar, one character longer than
+    /// previewed to tell whether it is truncated
+    preview: Option<Box<str>>,
+    /// The size of the keys in the subtree, including that of the node
+    key_size: usize,
+    /// The number of levels of containers in the subtree, set as it is built
+    /// and kept when it is pruned or transformed
+    depth: usize,
+    children: Option<Vec<Node>>,
+}
+
+/// The hashes of distinct scalar values; boxed, for the nodes not counting
+/// them to stay small.
+#[allow(clippy::box_collection)]
+type Values = Box<HashSet<u64>>;
+
+/// The tag of a node, shared by all the nodes of the same key.
+type Tag = Arc<str>;
+
+/// The tags given to the nodes of a size tree, so that the many nodes of the
+/// same key share a single copy of it.
+#[derive(Default)]
+struct Tags(Mutex<HashSet<Tag>>);
+impl Tags {
+    fn get(&self, tag: &str) -> Tag {
+        let mut tags = self.0.lock().unwrap();
+        if let Some(tag) = tags.get(tag) {
+            return tag.clone();
+        }
+        let tag = Tag::from(tag);
+        tags.insert(tag.clone());
+        tag
+    }
+}
+
+/// The depth of a container of `children`.
+fn depth(children: &[Node]) -> usize {
+    1 + children.iter().map(|c| c.depth).max().unwrap_or(0)
+}
+
+/// The union of the distinct values of `nodes`, if they are counted.
+fn distinct(nodes: &[Node]) -> Option<Values> {
+    nodes
+        .iter()
+        .filter_map(|n| n.values.as_deref())
+        .fold(None, |ax, values| {
+            let mut ax = ax.unwrap_or_default();
+            ax.extend(values);
+            Some(ax)
+        })
+}
+
+/// The elements of an array, aggregated one by one as they are parsed, so
+/// that they are never all held at once unless kept as children.
+#[derive(Default)]
+struct Elements {
+    len: usize,
+    size_b: usize,
+    size_c: usize,
+    size_s: usize,
+    size_m: usize,
+    size_l: usize,
+    size_u: usize,
+    size_z: usize,
+    size_t: usize,
+    key_size: usize,
+    values: Option<Values>,
+    /// The elements kept as children, if arrays are expanded
+    kept: Vec<Node>,
+    /// The merge of the elements, if aggregated in a schema
+    schema: Option<Node>,
+    /// Whether any element is a container
+    nested: bool,
+}
+impl Elements {
+    fn push(&mut self, element: Node, settings: &ParseSettings) {
+        let sampled = settings.sample.is_none_or(|sample| self.len < sample);
+        self.len += 1;
+        self.size_b += element.size_b;
+        self.size_c += element.size_c;
+        self.size_s += element.size_s;
+        self.size_m += element.size_m;
+        self.size_l += element.size_l;
+        self.size_u += element.size_u;
+        self.size_z += element.size_z;
+        self.size_t += element.size_t;
+        self.key_size += element.key_size;
+        if let Some(values) = element.values.as_deref() {
+            self.values
+                .get_or_insert_with(Default::default)
+                .extend(values);
+        }
+        if !sampled {
+            return;
+        }
+        if settings.expand_arrays {
+            self.kept.push(element);
+        } else if settings.element_schema {
+            self.nested |= element.children.is_some();
+            self.schema
+                .get_or_insert_with(|| Node::object("[*]".to_owned(), 0, Vec::new()))
+                .merge(element);
+        }
+    }
+
+    /// The node of the array, whose syntax takes `overhead` bytes.
+    fn into_node(
+        mut self,
+        tag: impl Into<Tag>,
+        ks: usize,
+        overhead: usize,
+        settings: &ParseSettings,
+    ) -> Node {
+        let children = if settings.expand_arrays {
+            self.kept.shrink_to_fit();
+            Some(self.kept)
+        } else if self.nested {
+            self.schema.map(|mut schema| {
+                schema.len = 0;
+                vec![schema]
+            })
+        } else {
+            None
+        };
+        Node {
+            tag: Some(tag.into()),
+            kind: Kind::Array,
+            len: self.len,
+            size_b: self.size_b,
+            size_c: self.len + self.size_c,
+            size_s: overhead + self.size_s,
+            size_m: memory::array(self.len) + self.size_m,
+            size_l: self.size_l,
+            size_u: self.size_u,
+            size_z: self.size_z,
+            size_t: self.size_t,
+            size_w: 0,
+            size_d: self.values.as_ref().map_or(0, |v| v.len()),
+            values: self.values,
+            matched: false,
+            preview: None,
+            key_size: ks + self.key_size,
+            depth: children.as_deref().map_or(0, depth),
+            children,
+        }
+    }
+}
+
+/// Containers with fewer children than this are walked sequentially, as
+/// spreading them over threads would cost more than it saves.
+const PARALLEL_WALK: usize = 256;
+
+/// A node of a document, to be walked as the child of its parent.
+struct Child<'a, D> {
+    n: &'a D,
+    /// The size of its key in the document encoding
+    ks: usize,
+    /// The size of its key once decoded, if it is an object entry
+    key: usize,
+    tag: Tag,
+    included: bool,
+}
+
+/// A container of a document whose children are being walked.
+struct Frame<'a, D> {
+    container: Child<'a, D>,
+    array: bool,
+    /// The children still to walk, in reverse order
+    pending: Vec<Child<'a, D>>,
+    /// The key sizes, decoded and encoded, of the child being walked
+    walking: (usize, usize),
+    /// The key sizes of the children walked so far
+    keys: Vec<(usize, usize)>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn from_document<D: Document>(
+        n: &D,
+        depth: usize,
+        tag: impl Into<Tag>,
+        settings: &ParseSettings,
+    ) -> Node {
+        let tag = tag.into();
+        Node::walk(
+            n,
+            0,
+            depth,
+            tag.clone(),
+            settings,
+            settings.include_keys.is_empty(),
+        )
+        .unwrap_or_else(|| Node::object(tag, 0, Vec::new()))
+    }
+
+    /// Build the size tree of `n`, keeping only the entries whose key is not
+    /// excluded, and, unless `included`, only those whose key or a descendant
+    /// key is included; return `None` if nothing is kept.
+    ///
+    /// Containers are walked with an explicit stack rather than recursively,
+    /// so that deeply nested documents can not overflow the call stack.
+    fn walk<D: Document>(
+        n: &D,
+        ks: usize,
+        depth: usize,
+        tag: Tag,
+        settings: &ParseSettings,
+        included: bool,
+    ) -> Option<Node> {
+        let root = Child {
+            n,
+            ks,
+            key: 0,
+            tag,
+            included,
+        };
+        let mut stack = Vec::new();
+        let mut walked = Node::enter(root, depth, settings, &mut stack);
+        loop {
+            if let Some(node) = walked.take() {
+                match stack.last_mut() {
+                    None => return node,
+                    Some(frame) => {
+                        if let Some(node) = node {
+                            frame.keys.push(frame.walking);
+                            frame.children.push(node);
+                        }
+                    }
+                }
+            }
+            let frame = stack.last_mut()?;
+            match frame.pending.pop() {
+                Some(child) => {
+                    frame.walking = (child.key, child.ks);
+                    walked = Node::enter(child, depth + stack.len(), settings, &mut stack);
+                }
+                None => {
+                    let frame = stack.pop()?;
+                    walked = Some(Node::close(frame, depth + stack.len(), settings));
+                }
+            }
+        }
+    }
+
+    /// Walk `child`, found at `depth`, if it is a scalar; otherwise, push it
+    /// on `stack` for its children to be walked next.
+    fn enter<'a, D: Document>(
+        child: Child<'a, D>,
+        depth: usize,
+        settings: &ParseSettings,
+        stack: &mut Vec<Frame<'a, D>>,
+    ) -> Option<Option<Node>> {
+        let included = child.included;
+        let (array, mut pending) = match child.n.shape() {
+            Shape::Scalar => return Some(Node::scalar(child, depth, settings)),
+            Shape::Array(children) => (
+                true,
+                children
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (ks, c))| {
+                        let tag = if settings.expand_arrays {
+                            settings.tags.get(
+                                &settings
+                                    .id_field
+                                    .as_ref()
+                                    .and_then(|field| match c.shape() {
+                                        Shape::Object(kvs) => kvs
+                                            .into_iter()
+                                            .find(|(k, _, _)| k == field)
+                                            .and_then(|(_, _, v)| v.label()),
+                                        _ => None,
+                                    })
+                                    .unwrap_or_else(|| i.to_string()),
+                            )
+                        } else {
+                            Tag::default()
+                        };
+                        Child {
+                            n: c,
+                            ks,
+                            key: 0,
+                            tag,
+                            included,
+                        }
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            Shape::Object(children) => (
+                false,
+                children
+                    .into_iter()
+                    .filter(|(k, _, _)| !settings.exclude_keys.iter().any(|r| r.is_match(k)))
+                    .map(|(k, ks, v)| Child {
+                        n: v,
+                        ks,
+                        key: k.len(),
+                        tag: settings.tags.get(&k),
+                        included: included || settings.include_keys.iter().any(|r| r.is_match(&k)),
+                    })
+                    .collect(),
+            ),
+        };
+        let (mut keys, mut children) = (Vec::new(), Vec::new());
+        if pending.len() >= PARALLEL_WALK && rayon::current_num_threads() > 1 {
+            // Each child is walked with its own stack
+            (keys, children) = std::mem::take(&mut pending)
+                .into_par_iter()
+                .filter_map(|c| {
+                    Node::walk(c.n, c.ks, depth + 1, c.tag, settings, c.included)
+                        .map(|node| ((c.key, c.ks), node))
+                })
+                .unzip();
+        }
+        pending.reverse();
+        stack.push(Frame {
+            container: child,
+            array,
+            pending,
+            walking: (0, 0),
+            keys,
+            children,
+        });
+        None
+    }
+
+    /// The leaf of the scalar `child`, found at `depth`.
+    fn scalar<D: Document>(
+        child: Child<D>,
+        depth: usize,
+        settings: &ParseSettings,
+    ) -> Option<Node> {
+        let Child {
+            n,
+            ks,
+            tag,
+            included,
+            ..
+        } = child;
+        if !included {
+            return None;
+        }
+        let mut leaf = Node::leaf(ks, n.size(), tag);
+        leaf.kind = n.kind();
+        leaf.size_s += n.overhead();
+        settings.advance(ks + leaf.size_s);
+        leaf.size_m = memory::scalar(n.heap());
+        leaf.size_u = n.chars();
+        if let Some((unit, _)) = settings.threshold {
+            settings
+                .weighed
+                .fetch_add(leaf.size(unit), Ordering::Relaxed);
+        }
+        if let Some(pattern) = &settings.grep {
+            leaf.matched = n.preview().is_some_and(|v| pattern.is_match(&v));
+        }
+        if let Some(len) = settings.preview {
+            leaf.preview = n
+                .preview()
+                .map(|s| s.chars().take(len + 1).collect::<String>().into());
+        }
+        if settings.distinct {
+            leaf.values = Some(Box::new(HashSet::from_iter(n.identity().map(|id| {
+                let mut hasher = DefaultHasher::new();
+                id.hash(&mut hasher);
+                hasher.finish()
+            }))));
+        }
+        Some(leaf.complete(n, true, depth, settings))
+    }
+
+    /// The node of a container, found at `depth`, whose children have all
+    /// been walked.
+    fn close<D: Document>(frame: Frame<D>, depth: usize, settings: &ParseSettings) -> Option<Node> {
+        let Frame {
+            container:
+                Child {
+                    n,
+                    ks,
+                    tag,
+                    included,
+                    ..
+                },
+            array,
+            keys,
+            children,
+            ..
+        } = frame;
+        let node = if array {
+            let overhead = n.overhead() + keys.iter().map(|(_, ks)| ks).sum::<usize>();
+            settings.advance(ks + overhead);
+            if !included && children.is_empty() {
+                return None;
+            }
+            Node::array(tag, ks, overhead, children, settings)
+        } else {
+            settings.advance(ks + n.overhead());
+            if !included && children.is_empty() {
+                return None;
+            }
+            Node::entries(tag, ks, n.overhead(), &keys, children)
+        };
+        Some(node.complete(n, false, depth, settings))
+    }
+
+    /// Add to the node of `n`, found at `depth`, the sizes requiring it to
+    /// be serialized, and prune it.
+    fn complete<D: Document>(
+        mut self,
+        n: &D,
+        scalar: bool,
+        depth: usize,
+        settings: &ParseSettings,
+    ) -> Node {
+        // Once pruned, a container is no longer serialized as in the document
+        let pruned = !settings.include_keys.is_empty() || !settings.exclude_keys.is_empty();
+        if (settings.compressed || settings.tokenizer.is_some()) && (scalar || !pruned) {
+            let content = n.serialize();
+            if settings.compressed {
+                self.size_z = content
+                    .as_ref()
+                    .map_or(self.size_s, |c| input::compressed_size(c));
+            }
+            if let (Some(tokenizer), Some(content)) = (settings.tokenizer, &content) {
+                self.size_t = tokenizer.count(content);
+            }
+        }
+        self.size_d = self.values.as_ref().map_or(0, |v| v.len());
+        self.prune(depth, settings);
+        self
+    }
+
+    /// Drop the descendants of this node, found at `depth`, that could not
+    /// be displayed anyway, keeping its sizes.
+    fn prune(&mut self, depth: usize, settings: &ParseSettings) {
+        if self.prunable(depth, settings) {
+            self.children = self.children.take().map(|_| Vec::new());
+        }
+    }
+
+    /// Whether the descendants of this node, found at `depth`, could not be
+    /// displayed anyway.
+    fn prunable(&self, depth: usize, settings: &ParseSettings) -> bool {
+        let deep = settings
+            .max_depth
+            .is_some_and(|max_depth| depth >= max_depth);
+        let light = settings
+            .min_size
+            .is_some_and(|(unit, min_size)| self.size(unit) < min_size);
+        // The whole document is not weighed yet, but weighs at least as much
+        // as the scalars weighed so far
+        let faint = settings.threshold.is_some_and(|(unit, threshold)| {
+            (self.size(unit) as f32) < threshold * settings.weighed.load(Ordering::Relaxed) as f32
+        });
+        deep || light || faint
+    }
+
+    /// The node of an array of `children`, whose syntax takes `overhead`
+    /// bytes; its elements are only kept if expanded or merged in a schema.
+    fn array(
+        tag: impl Into<Tag>,
+        ks: usize,
+        overhead: usize,
+        children: Vec<Node>,
+        settings: &ParseSettings,
+    ) -> Node {
+        let mut elements = Elements::default();
+        for child in children {
+            elements.push(child, settings);
+        }
+        elements.into_node(tag, ks, overhead, settings)
+    }
+
+    /// The node of an object of `children`, whose syntax takes `overhead`
+    /// bytes, and whose keys are `keys` long once decoded and encoded.
+    fn entries(
+        tag: impl Into<Tag>,
+        ks: usize,
+        overhead: usize,
+        keys: &[(usize, usize)],
+        children: Vec<Node>,
+    ) -> Node {
+        let mut object = Node::object(tag, ks, children);
+        object.size_s += keys.iter().map(|(_, ks)| ks).sum::<usize>() + overhead;
+        object.size_m += memory::object(keys.iter().map(|(k, _)| *k));
+        object
+    }
+
+    fn object(tag: impl Into<Tag>, key_size: usize, mut children: Vec<Node>) -> Node {
+        // Collected one by one, children may have a lot of spare capacity
+        children.shrink_to_fit();
+        let values = distinct(&children);
+        Node {
+            tag: Some(tag.into()),
+            kind: Kind::Object,
+            len: 0,
+            size_b: children.iter().map(|c| c.size_b).sum::<usize>(),
+            size_c: children.len() + children.iter().map(|c| c.size_c).sum::<usize>(),
+            size_s: children.iter().map(|c| c.size_s).sum::<usize>(),
+            size_m: children.iter().map(|c| c.size_m).sum::<usize>(),
+            size_l: children.iter().map(|c| c.size_l).sum::<usize>(),
+            size_u: children.iter().map(|c| c.size_u).sum::<usize>(),
+            size_z: children.iter().map(|c| c.size_z).sum::<usize>(),
+            size_t: children.iter().map(|c| c.size_t).sum::<usize>(),
+            size_w: children.iter().map(|c| c.size_w).sum::<usize>(),
+            size_d: values.as_ref().map_or(0, |v| v.len()),
+            values,
+            matched: false,
+            preview: None,
+            key_size: key_size + children.iter().map(|c| c.key_size).sum::<usize>(),
+            depth: depth(&children),
+            children: Some(children),
+        }
+    }
+
+    fn leaf(key_size: usize, size: usize, tag: Tag) -> Node {
+        Node {
+            tag: if tag.is_empty() { None } else { Some(tag) },
+            kind: Kind::Other,
+            len: 0,
+            size_b: size,
+            size_c: 0,
+            size_s: size,
+            size_m: 0,
+            size_l: 1,
+            size_u: size,
+            size_z: 0,
+            size_t: 0,
+            size_w: 0,
+            size_d: 0,
+            values: None,
+            matched: false,
+            preview: None,
+            key_size,
+            depth: 0,
+            children: None,
+        }
+    }
+
+    /// Add the sizes of `other` to this node, merging children by tag.
+    fn merge(&mut self, other: Node) {
+        // The nodes to merge with each node, one level at a time
+        let mut stack = vec![(self, vec![other])];
+        while let Some((node, others)) = stack.pop() {
+            // The nodes to merge with each child of the node, by index
+            let mut merged = Vec::<Vec<Node>>::new();
+            for mut other in others {
+                node.len += other.len;
+                node.size_b += other.size_b;
+                node.size_c += other.size_c;
+                node.size_s += other.size_s;
+                node.size_m += other.size_m;
+                node.size_l += other.size_l;
+                node.key_size += other.key_size;
+                node.size_u += other.size_u;
+                node.size_z += other.size_z;
+                node.size_t += other.size_t;
+                node.size_w += other.size_w;
+                node.depth = node.depth.max(other.depth);
+                node.matched |= other.matched;
+                if node.kind != other.kind {
+                    node.kind = Kind::Other;
+                }
+                if node.preview != other.preview {
+                    node.preview = None;
+                }
+                if let Some(values) = other.values.take() {
+                    let mine = node.values.get_or_insert_with(Default::default);
+                    mine.extend(*values);
+                    node.size_d = mine.len();
+                }
+                if let Some(others) = other.children.take() {
+                    let children = node.children.get_or_insert_with(Vec::new);
+                    for other in others {
+                        match children.iter().position(|c| c.tag == other.tag) {
+                            Some(i) => {
+                                if merged.len() <= i {
+                                    merged.resize_with(i + 1, Vec::new);
+                                }
+                                merged[i].push(other);
+                            }
+                            None => children.push(other),
+                        }
+                    }
+                }
+            }
+            stack.extend(
+                node.children
+                    .iter_mut()
+                    .flatten()
+                    .zip(merged)
+                    .filter(|(_, others)| !others.is_empty()),
+            );
+        }
+    }
+
+    fn size(&self, count: Unit) -> usize {
+        match count {
+            Unit::Bytes => self.size_b,
+            Unit::Children => self.size_c,
+            Unit::Serialized => self.size_s,
+            Unit::Memory => self.size_m,
+            Unit::Leaves => self.size_l,
+            Unit::Keys => self.key_size,
+            Unit::Chars => self.size_u,
+            Unit::Gzip => self.size_z,
+            Unit::Tokens => self.size_t,
+            Unit::Distinct => self.size_d,
+            Unit::Weight => self.size_w,
+        }
+    }
+
+    /// Evaluate `expr` on this node and all its descendants.
+    fn weigh(&mut self, expr: &Expr) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            node.size_w = expr.eval(node).max(0.).round() as usize;
+            stack.extend(node.children.iter_mut().flatten());
+        }
+    }
+
+    /// Whether this node is a scalar rather than an object or an array.
+    fn is_scalar(&self) -> bool {
+        self.children.is_none() && self.len == 0 && self.size_c == 0 && self.size_l == 1
+    }
+
+    /// Remove the scalar descendants of this node, keeping its sizes.
+    fn prune_scalars(&mut self) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            if let Some(children) = node.children.as_mut() {
+                children.retain(|c| !c.is_scalar());
+                stack.extend(children);
+            }
+        }
+    }
+
+    /// Merge the nodes having a single child carrying nearly all their
+    /// weight with this child, labelling them with their joined tags.
+    fn collapse_chains(&mut self, unit: Unit) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            while let Some([child]) = node.children.as_deref_mut() {
+                if (child.size(unit) as f64) < CHAIN_RATIO * node.size(unit) as f64 {
+                    break;
+                }
+                let mut child = node.children.take().unwrap().pop().unwrap();
+                node.tag = Some(
+                    format!(
+                        "{}.{}",
+                        node.tag.take().unwrap_or_default(),
+                        child.tag.take().unwrap_or_default()
+                    )
+                    .into(),
+                );
+                node.len = child.len;
+                node.depth = child.depth;
+                node.children = child.children.take();
+            }
+            stack.extend(node.children.iter_mut().flatten());
+        }
+    }
+
+    /// Remove the descendants of this node that neither are nor contain
+    /// an array of at least `min_len` elements, and return whether this node
+    /// is or contains one.
+    fn keep_arrays(&mut self, min_len: usize) -> bool {
+        let keeps = |node: &Node| {
+            node.children.as_ref().is_some_and(|c| !c.is_empty())
+                || (node.len > 0 && node.len >= min_len)
+        };
+        self.retain_descendants(keeps);
+        keeps(self)
+    }
+
+    /// Remove the descendants of this node for which `keep` is false, once
+    /// their own descendants have been filtered.
+    fn retain_descendants(&mut self, mut keep: impl FnMut(&Node) -> bool) {
+        let Some(children) = self.children.take() else {
+            return;
+        };
+        let reversed = |mut nodes: Vec<Node>| {
+            nodes.reverse();
+            nodes
+        };
+        // The containers being filtered, but for this node, with their
+        // children left to filter, in reverse order, and those kept so far
+        let mut stack = vec![(None, reversed(children), Vec::new())];
+        loop {
+            let (_, pending, kept) = stack.last_mut().unwrap();
+            if let Some(mut child) = pending.pop() {
+                match child.children.take() {
+                    Some(children) => stack.push((Some(child), reversed(children), Vec::new())),
+                    None if keep(&child) => kept.push(child),
+                    None => {}
+                }
+                continue;
+            }
+            let (parent, _, kept) = stack.pop().unwrap();
+            match parent {
+                None => {
+                    self.children = Some(kept);
+                    return;
+                }
+                Some(mut parent) => {
+                    parent.children = Some(kept);
+                    if keep(&parent) {
+                        stack.last_mut().unwrap().2.push(parent);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Keep only the `n` heaviest children of this node, summarizing the
+    /// others in a single node.
+    fn keep_heaviest(&mut self, n: usize, unit: Unit) {
+        let Some(children) = self.children.as_mut() else {
+            return;
+        };
+        if children.len() <= n {
+            return;
+        }
+        let mut order = (0..children.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| std::cmp::Reverse(children[i].size(unit)));
+        let mut kept = vec![false; children.len()];
+        for &i in &order[..n] {
+            kept[i] = true;
+        }
+        let (kept, dropped): (Vec<_>, Vec<_>) = std::mem::take(children)
+            .into_iter()
+            .enumerate()
+            .partition(|(i, _)| kept[*i]);
+        *children = kept.into_iter().map(|(_, c)| c).collect();
+        let tag = match dropped.len() {
+            1 => "(1 other)".to_owned(),
+            n => format!("({} others)", n),
+        };
+        let mut others = Node::object(tag, 0, Vec::new());
+        others.kind = dropped[0].1.kind;
+        others.children = None;
+        others.depth = 0;
+        for (_, mut child) in dropped {
+            child.children = None;
+            others.merge(child);
+        }
+        others.len = 0;
+        children.push(others);
+    }
+
+    /// Keep only the `n` heaviest children of this node and of its
+    /// descendants, summarizing the others in a single node.
+    fn top(&mut self, n: usize, unit: Unit) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            node.keep_heaviest(n, unit);
+            stack.extend(node.children.iter_mut().flatten());
+        }
+    }
+
+    /// Keep only the smallest set of heaviest children of this node and of
+    /// its descendants that covers `ratio` of their parent weight,
+    /// summarizing the others in a single node.
+    fn coverage(&mut self, ratio: f64, unit: Unit) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            if let Some(children) = &node.children {
+                let target = ratio * node.size(unit) as f64;
+                let mut sizes = children.iter().map(|c| c.size(unit)).collect::<Vec<_>>();
+                sizes.sort_by_key(|&s| std::cmp::Reverse(s));
+                let mut covered = 0;
+                let n = sizes
+                    .iter()
+                    .position(|s| {
+                        covered += s;
+                        covered as f64 >= target
+                    })
+                    .map_or(children.len(), |i| i + 1);
+                node.keep_heaviest(n, unit);
+            }
+            stack.extend(node.children.iter_mut().flatten());
+        }
+    }
+
+    /// Keep only the `k` heaviest elements of this array and of its
+    /// descendant arrays, summarizing the others in a single node.
+    fn array_top(&mut self, k: usize, unit: Unit) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            if node.len > 0 {
+                node.keep_heaviest(k, unit);
+            }
+            stack.extend(node.children.iter_mut().flatten());
+        }
+    }
+
+    /// Sort the children of this node and of its descendants.
+    fn sort(&mut self, order: SortOrder, unit: Unit) {
+        if order == SortOrder::Input {
+            return;
+        }
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            let Some(children) = node.children.as_mut() else {
+                continue;
+            };
+            match order {
+                SortOrder::Size => children.sort_by_key(|c| std::cmp::Reverse(c.size(unit))),
+                SortOrder::SizeAsc => children.sort_by_key(|c| c.size(unit)),
+                SortOrder::Name => children.sort_by(|a, b| a.tag.cmp(&b.tag)),
+                SortOrder::Input => unreachable!(),
+            }
+            stack.extend(children);
+        }
+    }
+
+    fn max_depth(&self) -> usize {
+        let mut max_depth = 0;
+        let mut stack = vec![(self, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            if let Some(children) = &node.children {
+                max_depth = max_depth.max(depth + 1);
+                stack.extend(children.iter().map(|c| (c, depth + 1)));
+            }
+        }
+        max_depth
+    }
+
+    /// The number of nodes in the tree rooted at this node.
+    fn count(&self) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            count += 1;
+            stack.extend(node.children.iter().flatten());
+        }
+        count
+    }
+}
+impl Drop for Node {
+    // Dropped recursively, deep trees would overflow the stack
+    fn drop(&mut self) {
+        let mut stack = self.children.take().unwrap_or_default();
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.children.take().into_iter().flatten());
+        }
+    }
+}
+
+/// How to parse `file`, as requested by the user.
+fn parse_settings(file: &str, args: &Args) -> ParseSettings {
+    // Otherwise, the nodes that will not be displayed are only weighed
+    let complete = args.complete || args.summary || args.collapse_chains || args.min_len.is_some();
+    ParseSettings {
+        relaxed: args.relaxed || input::is_relaxed(file),
+        fast: args.fast,
+        uncompressed: args.uncompressed,
+        proto_descriptor: args.proto_descriptor.clone(),
+        message_type: args.message_type.clone(),
+        compressed: args.uses(Unit::Gzip),
+        tokenizer: args.uses(Unit::Tokens).then_some(args.tokenizer),
+        distinct: args.uses(Unit::Distinct),
+        preview: args.preview,
+        grep: args.grep.clone(),
+        root: args.root.clone(),
+        filter: args.filter.clone(),
+        select: args.select.clone(),
+        expand_arrays: args.expand_arrays,
+        element_schema: args.element_schema,
+        sample: args.sample,
+        id_field: args.id_field.clone(),
+        include_keys: args.include_keys.clone(),
+        exclude_keys: args.exclude_keys.clone(),
+        progress: None,
+        tags: Tags::default(),
+        max_depth: args
+            .max_depth
+            .filter(|&d| !complete && d >= 0)
+            .map(|d| d as usize),
+        min_size: (!complete
+            && args.min_size > 0
+            && args.units[0] != Unit::Weight
+            && !args.element_schema)
+            .then_some((args.units[0], args.min_size)),
+        threshold: (!complete
+            && args.threshold > 0.
+            && args.units[0].additive()
+            && !args.element_schema)
+            .then_some((args.units[0], args.threshold / 100.)),
+        weighed: Default::default(),
+        max_parse_depth: args.max_parse_depth,
+    }
+}
+
+/// Inputs larger than this, in bytes, get a progress bar while parsed.
+const PROGRESS_THRESHOLD: usize = 64 << 20;
+
+/// A progress bar for parsing `file`, whose content is `len` bytes long.
+fn progress_bar(file: &str, len: usize, args: &Args) -> ProgressBar {
+    let bar = ProgressBar::new(len as u64).with_message(file.to_owned());
+    bar.set_style(
+        ProgressStyle::with_template("{msg} {wide_bar} {bytes}/{total_bytes} ({eta})").unwrap(),
+    );
+    args.progress.add(bar)
+}
+
+/// Parse the content of a single file into a size tree; files of a corpus
+/// are not at the root of the tree.
+fn load(
+    file: &str,
+    content: input::Content,
+    args: &Args,
+    tag: String,
+    corpus: bool,
+) -> Result<Node> {
+    let format = args.input_format.unwrap_or_else(|| {
+        if args.proto_descriptor.is_some() {
+            InputFormat::Protobuf
+        } else {
+            InputFormat::from_path(file)
+        }
+    });
+    let mut settings = parse_settings(file, args);
+    if corpus {
+        settings.max_depth = None;
+    }
+    let cache = if args.cache {
+        // Cached size trees are complete, to be rendered at any depth or
+        // threshold, and only pruned once loaded
+        let pruning = (
+            settings.max_depth.take(),
+            settings.min_size.take(),
+            settings.threshold.take(),
+        );
+        let path = cache::path(file, format, &settings);
+        (settings.max_depth, settings.min_size, settings.threshold) = pruning;
+        if let Some(Result::Ok(mut root)) = path.as_deref().map(|p| cache::load(p, &settings)) {
+            root.tag = Some(tag.into());
+            return Ok(root);
+        }
+        (settings.max_depth, settings.min_size, settings.threshold) = (None, None, None);
+        path
+    } else {
+        None
+    };
+
+    let content = input::decompress(content)?;
+    settings.progress =
+        (content.len() > PROGRESS_THRESHOLD).then(|| progress_bar(file, content.len(), args));
+    let root = format
+        .load(&content, &settings, tag)
+        .with_context(|| format!("while parsing `{}`", file));
+    if let Some(bar) = settings.progress {
+        bar.finish_and_clear();
+        args.progress.remove(&bar);
+    }
+    if let (Some(path), Result::Ok(root)) = (&cache, &root) {
+        if let Err(e) = cache::store(path, root) {
+            eprintln!("failed to cache `{}`: {}", file, e);
+        }
+    }
+    root
+}
+
+/// Parse and aggregate all the documents designated by `files`, then
+/// transform them as requested by the user.
+fn analyze(files: &[String], args: &Args) -> Result<Node> {
+    let mut root = parse(files, args)?;
+    transform(&mut root, args);
+    Ok(root)
+}
+
+/// Weigh, restrict and sort the size tree of `root` as requested by the user.
+fn transform(root: &mut Node, args: &Args) {
+    if let Some(expr) = &args.weight_expr {
+        root.weigh(expr);
+    }
+    if args.containers_only {
+        root.prune_scalars();
+    }
+    if let Some(min_len) = args.min_len {
+        root.keep_arrays(min_len);
+    }
+    if args.collapse_chains {
+        root.collapse_chains(args.units[0]);
+    }
+    if let Some(k) = args.array_top {
+        root.array_top(k, args.units[0]);
+    }
+    if let Some(percent) = args.coverage {
+        root.coverage(percent as f64 / 100., args.units[0]);
+    }
+    if let Some(n) = args.top {
+        root.top(n, args.units[0]);
+    }
+    let order = if args.invert {
+        SortOrder::SizeAsc
+    } else {
+        args.sort
+    };
+    root.sort(order, args.units[0]);
+}
+
+/// Parse and aggregate all the documents designated by `files`.
+fn parse(files: &[String], args: &Args) -> Result<Node> {
+    let read = |file: &str| {
+        // Watched files are rewritten, and mapping them could crash
+        input::read(file, &args.headers, !args.watch)
+            .with_context(|| format!("while reading `{}`", file))
+    };
+    let expanded = input::expand(files, args.recursive)?;
+    if expanded == files && files.len() == 1 && !input::is_archive(&files[0]) {
+        load(&files[0], read(&files[0])?, args, "Root".to_owned(), false)
+    } else {
+        let documents = expanded
+            .into_par_iter()
+            .map(|file| {
+                let contents = if input::is_archive(&file) {
+                    input::unpack(&file, read(&file)?)?
+                } else {
+                    let content = read(&file)?;
+                    vec![(file, content)]
+                };
+                contents
+                    .into_par_iter()
+                    .map(|(file, content)| {
+                        let name = Path::new(file.rsplit("::").next().unwrap_or(&file))
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| file.clone());
+                        let node = load(&file, content, args, name, true)?;
+                        Ok((file, node))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(input::corpus(
+            documents.into_iter().flatten().collect(),
+            "Root".to_owned(),
+        ))
+    }
+}
+
+/// The width of the reports: as requested, or that of the terminal, or as
+/// set in `COLUMNS` when not run in a terminal.
+fn width(args: &Args) -> usize {
+    args.width
+        .or_else(|| term_size::dimensions().map(|(w, _)| w))
+        .or_else(|| std::env::var("COLUMNS").ok()?.trim().parse().ok())
+        .unwrap_or(100)
+}
+
+/// How to render the analysis of `root` on a terminal `width` columns wide.
+fn display_settings(root: &Node, args: &Args, width: usize) -> DisplaySettings {
+    DisplaySettings {
+        counter: args.units[0],
+        columns: args.units[1..].to_vec(),
+        colorizer: args.colors,
+        gradient: args.gradient.clone(),
+        heat: args.heat.clone(),
+        palette: Palette::detect(),
         depth: args.max_depth.map(|d| {
             if d >= 0 {
                 d as usize
@@ -259,9 +2192,940 @@ fn main() -> Result<()> {
                 ((root.max_depth() as isize) + d - 1) as usize
             }
         }),
+        min_depth: args.min_depth,
+        threshold: args.threshold / 100.,
+        min_size: args.min_size,
         width,
+        ascii: args.ascii,
+        show_depth: args.show_depth,
+        show_types: args.show_types,
+        invert: args.invert,
+        show_paths: args.show_paths,
+        bar_char: args.bar_char.unwrap_or(if args.ascii { '#' } else { '▒' }),
+        bar_width: args.bar_width,
+        bar_scale: args.bar_scale,
+        bar_style: args.bar_style,
+        exact: args.exact,
+        parent_percent: args.parent_percent,
+        tree: args.tree,
+        preview: args.preview,
+        summary: args.summary,
+        header: !args.no_header,
+        max_lines: args.max_lines,
+        wrap: args.wrap,
+    }
+}
+
+/// Write the report in the requested format, possibly through a pager.
+fn report(
+    root: &Node,
+    settings: &DisplaySettings,
+    args: &Args,
+    height: Option<usize>,
+) -> Result<()> {
+    let mut report = Vec::new();
+    let out: Box<dyn Write> = match args.output.as_deref() {
+        None if height.is_some() => Box::new(&mut report),
+        None | Some("-") => Box::new(std::io::stdout().lock()),
+        Some(path) => {
+            let file = File::create(path).with_context(|| format!("while creating `{}`", path))?;
+            if !file.is_terminal() && args.color == ColorWhen::Auto {
+                colored::control::set_override(false);
+            }
+            Box::new(file)
+        }
+    };
+    let mut out = BufWriter::new(out);
+    render(root, settings, args.format, &mut out)?;
+    out.flush()?;
+    drop(out);
+    if let Some(height) = height {
+        output::pager::show(&report, height)?;
+    }
+    Ok(())
+}
+
+/// Render the analysis of `root` in `format`.
+fn render(
+    root: &Node,
+    settings: &DisplaySettings,
+    format: OutputFormat,
+    out: &mut dyn Write,
+) -> Result<()> {
+    match format {
+        OutputFormat::Table => output::table::render(root, settings, out),
+        OutputFormat::Json => output::json::render(root, settings, out),
+        OutputFormat::Csv => output::csv::render(root, settings, b',', out),
+        OutputFormat::Tsv => output::csv::render(root, settings, b'\t', out),
+        OutputFormat::Html => output::html::render(root, settings, out),
+        OutputFormat::SvgTreemap => output::treemap::render(root, settings, out),
+        OutputFormat::Folded => output::folded::render(root, settings, out),
+        OutputFormat::Markdown => output::markdown::render(root, settings, out),
+        OutputFormat::Ncdu => output::ncdu::render(root, settings, out),
+        OutputFormat::Du => output::du::render(root, settings, out),
+    }
+}
+
+/// Analyze the version of `files` committed at the git `revision`, as
+/// [`analyze`] would the files themselves.
+fn analyze_revision(files: &[String], revision: &str, args: &Args) -> Result<Node> {
+    let expanded = input::expand(files, args.recursive)?;
+    ensure!(
+        expanded
+            .iter()
+            .all(|f| f != "-" && !input::is_url(f) && !input::is_archive(f)),
+        "only local files can be read from git"
+    );
+    // Labelled by revision, the contents are not mistaken for the files in
+    // caches and errors
+    let load_at = |file: &str, tag: String, corpus: bool| {
+        let content = input::read_revision(file, revision)
+            .with_context(|| format!("while reading `{}` at `{}`", file, revision))?;
+        load(
+            &format!("{}:{}", revision, file),
+            content,
+            args,
+            tag,
+            corpus,
+        )
+    };
+    let mut root = if expanded == files && files.len() == 1 {
+        load_at(&files[0], "Root".to_owned(), false)?
+    } else {
+        let documents = expanded
+            .into_par_iter()
+            .map(|file| {
+                let name = Path::new(&file)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| file.clone());
+                let node = load_at(&file, name, true)?;
+                Ok((file, node))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        input::corpus(documents, "Root".to_owned())
+    };
+    transform(&mut root, args);
+    Ok(root)
+}
+
+/// Compare the analysis of the first of `files` with that of the others, or
+/// that of `files` at the git `revision` with that of `files`.
+fn diff(files: &[String], revision: Option<&str>, args: &Args) -> Result<()> {
+    ensure!(
+        matches!(args.format, OutputFormat::Table),
+        "diffs can only be rendered as tables"
+    );
+    let (old, new) = match revision {
+        Some(revision) => (
+            analyze_revision(files, revision, args)?,
+            analyze(files, args)?,
+        ),
+        None => {
+            ensure!(files.len() > 1, "two files are required to compare them");
+            (analyze(&files[..1], args)?, analyze(&files[1..], args)?)
+        }
     };
-    root.render(root.size(args.unit), 0, args.threshold / 100., &settings);
+    let settings = display_settings(&new, args, width(args));
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    output::diff::render(&old, &new, &settings, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Store the analysis of `files` as their latest snapshot.
+fn take_snapshot(files: &[String], args: &Args) -> Result<()> {
+    let root = parse(files, args)?;
+    let path = snapshot::take(files, &root)?;
+    println!("snapshot stored in `{}`", path.display());
+    Ok(())
+}
+
+/// Render how the size of the node at `pointer` changed across the snapshots
+/// of `files`.
+fn history(files: &[String], pointer: &str, args: &Args) -> Result<()> {
+    let snapshots = snapshot::list(files)?;
+    ensure!(
+        !snapshots.is_empty(),
+        "no snapshot of these files; take one with `toison snapshot`"
+    );
+    let settings = parse_settings(&files[0], args);
+    let mut sizes = Vec::new();
+    let mut latest = None;
+    for (time, path) in snapshots {
+        let mut root = snapshot::load(&path, &settings)
+            .with_context(|| format!("while loading `{}`", path.display()))?;
+        transform(&mut root, args);
+        sizes.push((
+            time,
+            snapshot::find(&root, pointer)?.map(|n| n.size(args.units[0])),
+        ));
+        latest = Some(root);
+    }
+    let settings = display_settings(latest.as_ref().unwrap(), args, width(args));
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    output::history::render(&sizes, &settings, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Analyze and render the files `runs` times, then report how long the
+/// fastest run took for each step, the peak memory used, and the size of the
+/// tree.
+fn bench(files: &[String], args: &Args, runs: usize) -> Result<()> {
+    let mut timings = [Duration::MAX; 3];
+    let mut tree = (0, 0);
+    for _ in 0..runs {
+        let start = Instant::now();
+        let mut root = parse(files, args)?;
+        let parsed = Instant::now();
+        transform(&mut root, args);
+        let settings = display_settings(&root, args, width(args));
+        let analyzed = Instant::now();
+        render(&root, &settings, args.format, &mut std::io::sink())?;
+        let rendered = Instant::now();
+        for (timing, elapsed) in
+            timings
+                .iter_mut()
+                .zip([parsed - start, analyzed - parsed, rendered - analyzed])
+        {
+            *timing = (*timing).min(elapsed);
+        }
+        tree = (root.count(), root.max_depth());
+    }
 
+    let mut out = std::io::stdout().lock();
+    for (step, timing) in ["parse", "analysis", "render"].iter().zip(timings) {
+        writeln!(out, "{:<12}{:>12.3} s", step, timing.as_secs_f64())?;
+    }
+    let peak = peak_memory().map_or_else(|| "unknown".to_owned(), |m| Unit::Bytes.format(m, false));
+    writeln!(out, "{:<12}{:>14}", "peak memory", peak)?;
+    writeln!(out, "{:<12}{:>14}", "nodes", tree.0.separate_with_commas())?;
+    writeln!(out, "{:<12}{:>14}", "depth", tree.1)?;
     Ok(())
 }
+
+/// The peak resident memory of the process, where the OS reports it.
+fn peak_memory() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    Some(kb * 1024)
+}
+
+/// The modification times of the files to analyze, to detect changes.
+fn timestamps(files: &[String], recursive: bool) -> Vec<(String, Option<SystemTime>)> {
+    input::expand(files, recursive)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|f| {
+            let mtime = std::fs::metadata(&f).and_then(|m| m.modified()).ok();
+            (f, mtime)
+        })
+        .collect()
+}
+
+/// Re-analyze and re-render the files whenever they change, until killed.
+fn watch(files: &[String], args: &Args) -> Result<()> {
+    ensure!(
+        files.iter().all(|f| f != "-" && !input::is_url(f)),
+        "only local files can be watched"
+    );
+    loop {
+        let before = timestamps(files, args.recursive);
+        if args.output.is_none() {
+            print!("\x1b[2J\x1b[H");
+        }
+        if let Err(e) = analyze(files, args).and_then(|root| {
+            report(
+                &root,
+                &display_settings(&root, args, width(args)),
+                args,
+                None,
+            )
+        }) {
+            eprintln!("Error: {:?}", e);
+        }
+        while timestamps(files, args.recursive) == before {
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    }
+}
+
+/// Aggregate the lines of an NDJSON file as they are appended to it, and
+/// periodically re-render the report, until killed.
+fn follow(files: &[String], args: &Args) -> Result<()> {
+    ensure!(
+        files.len() == 1 && files[0] != "-" && !input::is_url(&files[0]),
+        "only a single local file can be followed"
+    );
+    let path = &files[0];
+    ensure!(
+        matches!(
+            args.input_format
+                .unwrap_or_else(|| InputFormat::from_path(path)),
+            InputFormat::Ndjson
+        ),
+        "only NDJSON files can be followed"
+    );
+
+    let mut settings = parse_settings(path, args);
+    // Lines too light to be displayed may add up once merged
+    (settings.min_size, settings.threshold) = (None, None);
+    let empty = || Node::object("Root".to_owned(), 0, Vec::new());
+    let mut root = empty();
+    let mut offset = 0;
+    let mut pending = Vec::new();
+    let mut malformed = 0;
+    let mut dirty = true;
+    loop {
+        let mut file = File::open(path).with_context(|| format!("while opening `{}`", path))?;
+        let len = file.metadata()?.len();
+        if len < offset {
+            // The file has been truncated or replaced
+            (root, offset, malformed) = (empty(), 0, 0);
+            pending.clear();
+            dirty = true;
+        }
+        if len > offset {
+            file.seek(SeekFrom::Start(offset))?;
+            offset += file.read_to_end(&mut pending)? as u64;
+            let complete = pending
+                .iter()
+                .rposition(|&c| c == b'\n')
+                .map_or(0, |i| i + 1);
+            for line in String::from_utf8_lossy(&pending[..complete]).lines() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match input::parse_line(line, &settings) {
+                    Result::Ok(node) => {
+                        root.len += 1;
+                        root.size_c += 1;
+                        root.merge(node);
+                    }
+                    Err(_) => malformed += 1,
+                }
+            }
+            pending.drain(..complete);
+            dirty = true;
+        }
+
+        if dirty {
+            if args.output.is_none() {
+                print!("\x1b[2J\x1b[H");
+            }
+            let mut shown = root.clone();
+            transform(&mut shown, args);
+            report(
+                &shown,
+                &display_settings(&shown, args, width(args)),
+                args,
+                None,
+            )?;
+            if malformed > 0 {
+                eprintln!("{} malformed lines skipped", malformed);
+            }
+            dirty = false;
+        }
+        std::thread::sleep(FOLLOW_INTERVAL);
+    }
+}
+
+fn main() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let mut task = None;
+    let (mut args, interactive) = match cli.command {
+        Some(Command::Tui(mut args)) => {
+            args.complete = true;
+            (args, true)
+        }
+        Some(Command::Diff { git, mut args }) => {
+            // Otherwise, the nodes pruned from only one of the trees would
+            // seem added or removed
+            args.complete = true;
+            task = Some(Task::Diff(git));
+            (args, false)
+        }
+        Some(Command::Snapshot(mut args)) => {
+            // Snapshots may be followed down to any node
+            args.complete = true;
+            task = Some(Task::Snapshot);
+            (args, false)
+        }
+        Some(Command::History { pointer, mut args }) => {
+            args.complete = true;
+            task = Some(Task::History(pointer));
+            (args, false)
+        }
+        Some(Command::Bench { runs, args }) => {
+            ensure!(runs > 0, "at least one run is required");
+            task = Some(Task::Bench(runs));
+            (args, false)
+        }
+        Some(Command::Grep { pattern, mut args }) => {
+            // Every match must be reachable by its path
+            args.expand_arrays = true;
+            args.element_schema = false;
+            args.grep = Some(pattern);
+            args.complete = true;
+            (args, false)
+        }
+        None => (cli.args, false),
+    };
+    if let Some(theme) = args.theme.clone() {
+        // The appearance flags are those of the subcommand, if any
+        let matches = matches.subcommand().map_or(&matches, |(_, m)| m);
+        theme::apply(&theme, &mut args, matches)
+            .with_context(|| format!("while loading theme `{}`", theme))?;
+    }
+    let files = if args.files.is_empty() {
+        ensure!(
+            !std::io::stdin().is_terminal(),
+            "no input file given, and stdin is a terminal"
+        );
+        vec!["-".to_owned()]
+    } else {
+        args.files.clone()
+    };
+    if args.weight_expr.is_some() {
+        if !args.units.contains(&Unit::Weight) {
+            args.units.insert(0, Unit::Weight);
+        }
+    } else {
+        ensure!(
+            !args.units.contains(&Unit::Weight),
+            "the weight unit requires --weight-expr"
+        );
+    }
+    ensure!(
+        args.gradient.is_none() || args.colors == Colorizer::Gradient,
+        "--gradient requires --colors gradient"
+    );
+    // Otherwise, colored follows NO_COLOR, CLICOLOR_FORCE and whether stdout
+    // is a terminal
+    match args.color {
+        _ if args.ascii => colored::control::set_override(false),
+        ColorWhen::Always => colored::control::set_override(true),
+        ColorWhen::Never => colored::control::set_override(false),
+        ColorWhen::Auto => {}
+    }
+    if args.follow {
+        ensure!(!interactive, "--follow is not available in the TUI");
+        return follow(&files, &args);
+    }
+    if args.watch {
+        ensure!(!interactive, "--watch is not available in the TUI");
+        return watch(&files, &args);
+    }
+
+    match task {
+        Some(Task::Diff(revision)) => return diff(&files, revision.as_deref(), &args),
+        Some(Task::Snapshot) => return take_snapshot(&files, &args),
+        Some(Task::History(pointer)) => return history(&files, &pointer, &args),
+        Some(Task::Bench(runs)) => return bench(&files, &args, runs),
+        None => {}
+    }
+
+    if let Some(pattern) = &args.grep {
+        let mut root = parse(&files, &args)?;
+        if let Some(expr) = &args.weight_expr {
+            root.weigh(expr);
+        }
+        let settings = display_settings(&root, &args, width(&args));
+        let mut out = BufWriter::new(std::io::stdout().lock());
+        return grep::run(&root, pattern, &settings, &mut out);
+    }
+
+    let root = analyze(&files, &args)?;
+    let height = term_size::dimensions().map_or(usize::MAX, |(_, h)| h);
+    let settings = display_settings(&root, &args, width(&args));
+    if interactive {
+        return tui::run(&root, settings);
+    }
+
+    let paged = args.output.is_none() && !args.no_pager && std::io::stdout().is_terminal();
+    report(&root, &settings, &args, paged.then_some(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How deep the documents of the tests on deep trees nest.
+    const DEEP: usize = 3000;
+
+    /// A document nesting `levels` objects, or arrays if `arrays`.
+    fn deep(levels: usize, arrays: bool) -> String {
+        let (open, close) = if arrays {
+            ("[", "]")
+        } else {
+            (r#"{"a":"#, "}")
+        };
+        format!("{}1{}", open.repeat(levels), close.repeat(levels))
+    }
+
+    /// A small document, whose repeated values weigh less together than
+    /// apart in non-additive units.
+    const SAMPLE: &str = r#"{"a":{"b":[1,2,3],"c":"hello hello hello"},"d":{"e":{"f":"hello hello"}},"g":[{"x":1},{"x":1}]}"#;
+
+    /// Parse and transform `content` with the command-line `options`.
+    fn parsed(content: impl Into<Vec<u8>>, options: &[&str]) -> (Node, Args) {
+        let mut args = Cli::try_parse_from(
+            ["toison", "--max-parse-depth", "100000"]
+                .iter()
+                .chain(options),
+        )
+        .unwrap()
+        .args;
+        if args.weight_expr.is_some() {
+            args.units.insert(0, Unit::Weight);
+        }
+        let mut root = load(
+            "test.json",
+            content.into().into(),
+            &args,
+            "Root".to_owned(),
+            false,
+        )
+        .unwrap();
+        transform(&mut root, &args);
+        (root, args)
+    }
+
+    /// The report on `content` in `format`, with the command-line `options`.
+    fn rendered(content: &str, options: &[&str], format: OutputFormat) -> String {
+        let (root, args) = parsed(content.to_owned(), options);
+        let settings = display_settings(&root, &args, 100);
+        let mut out = Vec::new();
+        render(&root, &settings, format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Parse, transform and render `content` in every format, with the
+    /// command-line `options`, on a stack too small for deep trees to be
+    /// walked recursively.
+    fn render_all(content: String, options: &'static [&'static str]) {
+        std::thread::Builder::new()
+            .stack_size(1 << 20)
+            .spawn(move || {
+                let (root, args) = parsed(content, options);
+                let settings = display_settings(&root, &args, 100);
+                for format in OutputFormat::value_variants() {
+                    render(&root, &settings, *format, &mut std::io::sink()).unwrap();
+                }
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn deep_trees_render_in_every_format() {
+        render_all(deep(DEEP, false), &[]);
+        render_all(deep(DEEP, true), &["--expand-arrays"]);
+    }
+
+    #[test]
+    fn deep_trees_are_transformed() {
+        render_all(deep(DEEP, false), &["--top", "1"]);
+        render_all(deep(DEEP, false), &["--coverage", "50"]);
+        render_all(deep(DEEP, false), &["--weight-expr", "bytes + 8*children"]);
+        render_all(deep(DEEP, false), &["--min-len", "1"]);
+        render_all(
+            deep(DEEP, false),
+            &["--containers-only", "--collapse-chains", "--sort", "name"],
+        );
+        let elements = format!("[{0},{0}]", deep(DEEP, false));
+        render_all(elements, &["--element-schema", "--sort", "size"]);
+        render_all(deep(DEEP, true), &["--expand-arrays", "--array-top", "1"]);
+    }
+
+    #[test]
+    fn folded_stacks_in_non_additive_units() {
+        for unit in ["gzip", "distinct"] {
+            let folded = rendered(SAMPLE, &["-u", unit], OutputFormat::Folded);
+            assert!(!folded.is_empty());
+            for line in folded.lines() {
+                let (_, size) = line.rsplit_once(' ').unwrap();
+                assert!(size.parse::<usize>().unwrap() > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn ncdu_export_in_non_additive_units() {
+        for unit in ["gzip", "distinct"] {
+            let export = rendered(SAMPLE, &["-u", unit], OutputFormat::Ncdu);
+            let export: serde_json::Value = serde_json::from_str(&export).unwrap();
+            assert_eq!(export[3][0]["name"], "Root");
+        }
+    }
+
+    #[test]
+    fn hidden_nodes_are_pruned_while_parsed() {
+        let content = r#"{"a":"a heavy value, weighed before the others","b":{"c":{"d":1}}}"#;
+        for options in [["--max-depth", "1"], ["--threshold", "50"]] {
+            let (pruned, _) = parsed(content.to_owned(), &options);
+            let (complete, _) = parsed(content.to_owned(), &[options[0], options[1], "--summary"]);
+            assert!(pruned.count() < complete.count());
+            assert_eq!(pruned.depth, complete.depth);
+            assert_eq!(
+                rendered(content, &options, OutputFormat::Csv),
+                rendered(
+                    content,
+                    &[options[0], options[1], "--summary"],
+                    OutputFormat::Csv
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn streamed_trees_match_the_dom_walk() {
+        let escaped = r#"{"é":"a\"b\u00e9","n":[-1.5e3,0,null,true],"o":{"":{}}}"#;
+        // Concatenated documents are left by simd-json to serde_json
+        let concatenated = format!("{}\n{}\n", SAMPLE, escaped);
+        for (content, format) in [
+            (SAMPLE, "json"),
+            (escaped, "json"),
+            (&concatenated, "json"),
+            (&concatenated, "ndjson"),
+        ] {
+            for options in [&[][..], &["--threshold", "20"], &["--expand-arrays"]] {
+                let options = [options, &["--input-format", format]].concat();
+                // A root is only handled by the DOM walk
+                let dom = [&options[..], &["--root", ""]].concat();
+                let fast = [&options[..], &["--fast"]].concat();
+                let expected = rendered(content, &dom, OutputFormat::Json);
+                assert_eq!(rendered(content, &options, OutputFormat::Json), expected);
+                assert_eq!(rendered(content, &fast, OutputFormat::Json), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn keys_are_sorted_as_in_the_document() {
+        for (format, content) in [
+            ("json", r#"{"b":1,"a":2}"#),
+            ("yaml", "b: 1\na: 2\n"),
+            ("toml", "b = 1\na = 2\n"),
+        ] {
+            let csv = rendered(
+                content,
+                &["--input-format", format, "--sort", "input"],
+                OutputFormat::Csv,
+            );
+            assert!(csv.find("/b,").unwrap() < csv.find("/a,").unwrap());
+        }
+    }
+
+    #[test]
+    fn binary_sizes_add_up_to_the_document() {
+        fn msgpack(v: &serde_json::Value) -> rmpv::Value {
+            match v {
+                serde_json::Value::Array(xs) => {
+                    rmpv::Value::Array(xs.iter().map(msgpack).collect())
+                }
+                serde_json::Value::Object(kvs) => rmpv::Value::Map(
+                    kvs.iter()
+                        .map(|(k, v)| (k.as_str().into(), msgpack(v)))
+                        .collect(),
+                ),
+                serde_json::Value::String(s) => s.as_str().into(),
+                serde_json::Value::Number(x) => x.as_i64().unwrap().into(),
+                serde_json::Value::Bool(b) => (*b).into(),
+                serde_json::Value::Null => rmpv::Value::Nil,
+            }
+        }
+
+        fn bson(v: &serde_json::Value) -> bson::Bson {
+            match v {
+                serde_json::Value::Array(xs) => bson::Bson::Array(xs.iter().map(bson).collect()),
+                serde_json::Value::Object(kvs) => {
+                    bson::Bson::Document(kvs.iter().map(|(k, v)| (k.clone(), bson(v))).collect())
+                }
+                serde_json::Value::String(s) => s.as_str().into(),
+                serde_json::Value::Number(x) => x.as_i64().unwrap().into(),
+                serde_json::Value::Bool(b) => (*b).into(),
+                serde_json::Value::Null => bson::Bson::Null,
+            }
+        }
+
+        let document = serde_json::json!({
+            "short": [1, 2, 3],
+            "long": (0..300).collect::<Vec<_>>(),
+            "wide": (0..20).map(|i| (format!("k{}", i), i.into())).collect::<serde_json::Map<_, _>>(),
+            "nested": {"k": {"a": "x".repeat(40), "b": null, "c": [true, -70000]}},
+        });
+        let mut encoded = Vec::new();
+        rmpv::encode::write_value(&mut encoded, &msgpack(&document)).unwrap();
+        let (root, _) = parsed(encoded.clone(), &["--input-format", "msgpack"]);
+        assert_eq!(root.size_s, encoded.len());
+
+        let mut encoded = Vec::new();
+        ciborium::into_writer(&document, &mut encoded).unwrap();
+        let (root, _) = parsed(encoded.clone(), &["--input-format", "cbor"]);
+        assert_eq!(root.size_s, encoded.len());
+
+        let bson::Bson::Document(converted) = bson(&document) else {
+            unreachable!()
+        };
+        let encoded = converted.to_vec().unwrap();
+        let (root, _) = parsed(encoded.clone(), &["--input-format", "bson"]);
+        assert_eq!(root.size_s, encoded.len());
+        let dump = [encoded.as_slice(), &encoded].concat();
+        let (root, _) = parsed(dump.clone(), &["--input-format", "bson"]);
+        assert_eq!(root.size_s, dump.len());
+    }
+
+    #[test]
+    fn serialized_sizes_add_up_to_the_minified_document() {
+        let json = r#"{ "é": "a\"b\u00e9\n", "n": [-1.5e3, 0, null, true, 1.0], "o": {"": {}} }"#;
+        let minified =
+            serde_json::to_string(&serde_json::from_str::<serde_json::Value>(json).unwrap())
+                .unwrap()
+                .len();
+        // Streamed, with simd-json, and walked from a root
+        for options in [&[][..], &["--fast"], &["--root", ""]] {
+            let (root, _) = parsed(json, options);
+            assert_eq!(root.size_s, minified);
+        }
+
+        let document = serde_json::json!({"a": "x y", "b": [1, 2.5, {"c": false}], "d": {}});
+        let minified = document.to_string().len();
+        for (format, content) in [
+            ("yaml", "a: x y\nb: [1, 2.5, {c: false}]\nd: {}\n"),
+            ("toml", "a = \"x y\"\nb = [1, 2.5, {c = false}]\n[d]\n"),
+        ] {
+            let (root, _) = parsed(content, &["--input-format", format]);
+            assert_eq!(root.size_s, minified);
+        }
+    }
+
+    #[test]
+    fn protobuf_sizes_add_up_to_the_message() {
+        use prost_reflect::prost::Message;
+        use prost_reflect::prost_types::{
+            field_descriptor_proto::{Label, Type},
+            DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+            MessageOptions,
+        };
+        use prost_reflect::{DescriptorPool, DynamicMessage, MapKey, Value};
+
+        fn field(
+            name: &str,
+            number: i32,
+            label: Label,
+            kind: Type,
+            message: &str,
+        ) -> FieldDescriptorProto {
+            FieldDescriptorProto {
+                name: Some(name.to_owned()),
+                number: Some(number),
+                label: Some(label as i32),
+                r#type: Some(kind as i32),
+                type_name: (!message.is_empty()).then(|| message.to_owned()),
+                ..Default::default()
+            }
+        }
+        fn entry(name: &str, value: Type, message: &str) -> DescriptorProto {
+            DescriptorProto {
+                name: Some(name.to_owned()),
+                field: vec![
+                    field("key", 1, Label::Optional, Type::String, ""),
+                    field("value", 2, Label::Optional, value, message),
+                ],
+                options: Some(MessageOptions {
+                    map_entry: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        }
+
+        let inner = DescriptorProto {
+            name: Some("Inner".to_owned()),
+            field: vec![
+                field("a", 1, Label::Optional, Type::String, ""),
+                field("c", 2, Label::Repeated, Type::Int32, ""),
+            ],
+            ..Default::default()
+        };
+        let outer = DescriptorProto {
+            name: Some("Outer".to_owned()),
+            field: vec![
+                field("nested", 1, Label::Optional, Type::Message, ".t.Inner"),
+                field("items", 2, Label::Repeated, Type::Message, ".t.Inner"),
+                field(
+                    "by_name",
+                    3,
+                    Label::Repeated,
+                    Type::Message,
+                    ".t.Outer.ByNameEntry",
+                ),
+                field(
+                    "counts",
+                    4,
+                    Label::Repeated,
+                    Type::Message,
+                    ".t.Outer.CountsEntry",
+                ),
+                field("n", 20, Label::Optional, Type::Int32, ""),
+            ],
+            nested_type: vec![
+                entry("ByNameEntry", Type::Message, ".t.Inner"),
+                entry("CountsEntry", Type::Int64, ""),
+            ],
+            ..Default::default()
+        };
+        let descriptors = FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("t.proto".to_owned()),
+                package: Some("t".to_owned()),
+                message_type: vec![inner, outer],
+                syntax: Some("proto3".to_owned()),
+                ..Default::default()
+            }],
+        };
+        let pool = DescriptorPool::from_file_descriptor_set(descriptors.clone()).unwrap();
+
+        let inner = |a: &str, c: &[i32]| {
+            let mut m = DynamicMessage::new(pool.get_message_by_name("t.Inner").unwrap());
+            m.set_field_by_name("a", Value::String(a.to_owned()));
+            m.set_field_by_name("c", Value::List(c.iter().map(|x| Value::I32(*x)).collect()));
+            Value::Message(m)
+        };
+        let mut outer = DynamicMessage::new(pool.get_message_by_name("t.Outer").unwrap());
+        outer.set_field_by_name("nested", inner(&"x".repeat(200), &[1, -2, 300]));
+        outer.set_field_by_name(
+            "items",
+            Value::List(
+                (0..5)
+                    .map(|i| inner(&"y".repeat(i * 40), &[i as i32]))
+                    .collect(),
+            ),
+        );
+        outer.set_field_by_name(
+            "by_name",
+            Value::Map(
+                (0..3)
+                    .map(|i| (MapKey::String(format!("k{}", i)), inner("z", &[70000; 50])))
+                    .collect(),
+            ),
+        );
+        outer.set_field_by_name(
+            "counts",
+            Value::Map([(MapKey::String("n".to_owned()), Value::I64(-1))].into()),
+        );
+        outer.set_field_by_name("n", Value::I32(7));
+        let encoded = outer.encode_to_vec();
+
+        let descriptor = std::env::temp_dir().join(format!("toison-{}.pb", std::process::id()));
+        std::fs::write(&descriptor, descriptors.encode_to_vec()).unwrap();
+        let (root, _) = parsed(
+            encoded.clone(),
+            &[
+                "--input-format",
+                "protobuf",
+                "--proto-descriptor",
+                descriptor.to_str().unwrap(),
+                "--message-type",
+                "t.Outer",
+            ],
+        );
+        std::fs::remove_file(&descriptor).unwrap();
+        assert_eq!(root.size_s, encoded.len());
+    }
+
+    #[test]
+    fn snapshots_are_loaded_as_taken() {
+        let data = std::env::temp_dir().join(format!("toison-{}", std::process::id()));
+        std::env::set_var("XDG_DATA_HOME", &data);
+        let files = ["test.json".to_owned()];
+        let (_, args) = parsed(SAMPLE, &[]);
+        let root = load(
+            "test.json",
+            SAMPLE.as_bytes().to_vec().into(),
+            &args,
+            "Root".to_owned(),
+            false,
+        )
+        .unwrap();
+        let path = snapshot::take(&files, &root).unwrap();
+        let snapshots = snapshot::list(&files).unwrap();
+        assert_eq!(
+            snapshots.iter().map(|(_, p)| p).collect::<Vec<_>>(),
+            [&path]
+        );
+
+        let mut loaded = snapshot::load(&path, &parse_settings("test.json", &args)).unwrap();
+        std::fs::remove_dir_all(&data).unwrap();
+        transform(&mut loaded, &args);
+        let mut out = Vec::new();
+        render(
+            &loaded,
+            &display_settings(&loaded, &args, 100),
+            OutputFormat::Json,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            rendered(SAMPLE, &[], OutputFormat::Json)
+        );
+
+        let size = |pointer| snapshot::find(&loaded, pointer).unwrap().map(|n| n.size_b);
+        assert_eq!(size("/"), Some(loaded.size_b));
+        assert_eq!(size("/d/e/f"), Some(11));
+        assert_eq!(size("/d/x"), None);
+        assert!(snapshot::find(&loaded, "d/e").is_err());
+    }
+
+    #[test]
+    fn json_reports_follow_their_schema() {
+        let report = rendered(r#"{"a":{"b":[1,2]},"c":"xy"}"#, &[], OutputFormat::Json);
+        let root = serde_json::from_str::<serde_json::Value>(&report).unwrap();
+        assert_eq!(
+            root.as_object().unwrap().keys().collect::<Vec<_>>(),
+            [
+                "path", "tag", "depth", "size_b", "size_c", "size_s", "size_m", "size_l", "size_u",
+                "size_z", "size_t", "size_d", "key_size", "percent", "children",
+            ]
+        );
+
+        let mut nodes = Vec::new();
+        let mut stack = vec![&root];
+        while let Some(node) = stack.pop() {
+            nodes.push((
+                node["path"].as_str().unwrap(),
+                node["tag"].as_str().unwrap(),
+                node["depth"].as_u64().unwrap(),
+                node["size_b"].as_u64().unwrap(),
+                node["size_c"].as_u64().unwrap(),
+                node["key_size"].as_u64().unwrap(),
+                node["percent"].as_f64().unwrap(),
+                node.get("len").map(|l| l.as_u64().unwrap()),
+            ));
+            if let Some(children) = node.get("children") {
+                stack.extend(children.as_array().unwrap().iter().rev());
+            }
+        }
+        assert_eq!(
+            nodes,
+            [
+                ("", "Root", 0, 4, 5, 3, 100.0, None),
+                ("/a", "a", 1, 2, 3, 2, 50.0, None),
+                ("/a/b", "b", 2, 2, 2, 1, 50.0, Some(2)),
+                ("/c", "c", 1, 2, 0, 1, 50.0, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_documents_are_shown() {
+        let table = rendered(r#"{"a":null,"b":[]}"#, &[], OutputFormat::Table);
+        assert!(table.lines().any(|l| l.trim_start().starts_with("b ")));
+    }
+}
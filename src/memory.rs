@@ -0,0 +1,61 @@
+//! Estimate the memory footprint of documents once deserialized as
+//! `serde_json::Value`, with the `preserve_order` feature.
+use serde_json::Value;
+use std::mem::size_of;
+
+/// The inline size of a value, in its parent container or on the stack.
+const SLOT: usize = size_of::<Value>();
+/// An entry of an `IndexMap`: the hash, the key, and the value.
+const ENTRY: usize = size_of::<u64>() + size_of::<String>() + SLOT;
+
+/// The size of the heap chunk actually reserved by the allocator for a
+/// request of `bytes`, modeled after glibc `malloc`.
+pub fn alloc(bytes: usize) -> usize {
+    if bytes == 0 {
+        0
+    } else {
+        ((bytes + 8 + 15) & !15).max(32)
+    }
+}
+
+/// The capacity of a `Vec` grown by pushing `n` elements one at a time.
+fn grown_capacity(n: usize) -> usize {
+    if n == 0 {
+        0
+    } else {
+        n.next_power_of_two().max(4)
+    }
+}
+
+/// The footprint of a scalar owning `heap` bytes.
+pub fn scalar(heap: usize) -> usize {
+    SLOT + heap
+}
+
+/// The footprint of an array of `n` elements, excluding their own.
+pub fn array(n: usize) -> usize {
+    SLOT + alloc(grown_capacity(n) * SLOT) - n * SLOT
+}
+
+/// The footprint of an object with keys of the given lengths, excluding
+/// that of its values.
+pub fn object(keys: impl Iterator<Item = usize>) -> usize {
+    let (n, keys) = keys.fold((0_usize, 0), |(n, heap), k| (n + 1, heap + alloc(k)));
+    if n == 0 {
+        return SLOT;
+    }
+    // hashbrown tables keep a load factor under 7/8, and a group of control
+    // bytes after the buckets
+    let buckets = match n {
+        1..4 => 4,
+        4..8 => 8,
+        _ => (n * 8 / 7).next_power_of_two(),
+    };
+    let capacity = if buckets < 8 {
+        buckets - 1
+    } else {
+        buckets / 8 * 7
+    };
+    let table = alloc(buckets * size_of::<usize>() + buckets + 16);
+    SLOT + alloc(capacity * ENTRY) - n * SLOT + table + keys
+}
@@ -0,0 +1,74 @@
+use crate::{DisplaySettings, Node};
+use anyhow::*;
+use std::io::Write;
+
+/// Write one row per displayed node, separated by `delimiter`.
+pub fn render(
+    root: &Node,
+    settings: &DisplaySettings,
+    delimiter: u8,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(out);
+    writer.write_record([
+        "path", "tag", "depth", "len", "size_b", "size_c", "size_s", "size_m", "size_l", "size_u",
+        "size_z", "size_t", "size_d", "key_size", "percent",
+    ])?;
+    write_rows(
+        root,
+        String::new(),
+        0,
+        root.size(settings.counter),
+        settings,
+        &mut writer,
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_rows<W: Write>(
+    root: &Node,
+    path: String,
+    depth: usize,
+    total_size: usize,
+    settings: &DisplaySettings,
+    writer: &mut csv::Writer<W>,
+) -> Result<()> {
+    // The nodes still to write, with their path and depth; the next one last
+    let mut stack = vec![(root, path, depth)];
+    while let Some((node, path, depth)) = stack.pop() {
+        if !settings.shows(node, depth, total_size) {
+            continue;
+        }
+
+        if settings.prints(depth) {
+            writer.write_record([
+                path.clone(),
+                node.tag.as_deref().unwrap_or_default().to_owned(),
+                depth.to_string(),
+                node.len.to_string(),
+                node.size_b.to_string(),
+                node.size_c.to_string(),
+                node.size_s.to_string(),
+                node.size_m.to_string(),
+                node.size_l.to_string(),
+                node.size_u.to_string(),
+                node.size_z.to_string(),
+                node.size_t.to_string(),
+                node.size_d.to_string(),
+                node.key_size.to_string(),
+                format!(
+                    "{:.4}",
+                    100. * node.size(settings.counter) as f64 / total_size as f64
+                ),
+            ])?;
+        }
+        for c in node.children.iter().flatten().rev() {
+            let path = super::child_path(&path, c.tag.as_deref().unwrap_or_default());
+            stack.push((c, path, depth + 1));
+        }
+    }
+    Ok(())
+}
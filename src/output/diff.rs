@@ -0,0 +1,172 @@
+//! Compare the size trees of two versions of a document, node by node.
+use crate::{DisplaySettings, Node};
+use anyhow::*;
+use colored::Colorize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Write;
+use thousands::Separable;
+
+/// The columns taken by the sizes, the change and its ratio.
+const W_NUMBERS: usize = 11 + 1 + 11 + 1 + 12 + 1 + 9;
+
+/// A node of the old tree and the node at the same path in the new one; either
+/// may be missing.
+type Pair<'a> = (Option<&'a Node>, Option<&'a Node>);
+
+/// Write the displayed nodes of `old` and `new`, matched by path, with the
+/// change of their size; the nodes that changed the most come first.
+pub fn render(
+    old: &Node,
+    new: &Node,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let unit = settings.counter;
+    let w_tagline = settings.width.saturating_sub(W_NUMBERS + 1);
+    if settings.header {
+        let label = if settings.show_paths { "path" } else { "key" };
+        let header = format!(
+            "{} {:>11} {:>11} {:>12} {:>9}",
+            settings.fit(label, w_tagline),
+            "old",
+            "new",
+            "change",
+            "%"
+        );
+        writeln!(out, "{}", header.bold())?;
+    }
+
+    let size = |n: Option<&Node>| n.map_or(0, |n| n.size(unit));
+    let total_size = old.size(unit).max(new.size(unit));
+    let mut lines = 0;
+    // The pairs still to write, with their path and depth; the next one last
+    let mut stack = vec![((Some(old), Some(new)), String::new(), 0)];
+    while let Some((pair, path, depth)) = stack.pop() {
+        let shown = |pair: &Pair, depth| {
+            settings.shows_size(size(pair.0).max(size(pair.1)), depth, total_size)
+        };
+        if !shown(&pair, depth) {
+            continue;
+        }
+        if settings.prints(depth) {
+            if settings.max_lines.is_none_or(|max| lines < max) {
+                write_row(pair, &path, depth, w_tagline, settings, out)?;
+            }
+            lines += 1;
+        }
+        let mut children = children(pair);
+        children.retain(|child| shown(child, depth + 1));
+        children.sort_by_key(|&(o, n)| std::cmp::Reverse(size(o).abs_diff(size(n))));
+        for child in children.into_iter().rev() {
+            let tag = child.1.or(child.0).and_then(|c| c.tag.as_deref());
+            let path = crate::output::child_path(&path, tag.unwrap_or_default());
+            stack.push((child, path, depth + 1));
+        }
+    }
+    let printed = settings.max_lines.map_or(lines, |max| lines.min(max));
+    if lines > printed {
+        writeln!(
+            out,
+            "{} {} more rows",
+            if settings.ascii { "..." } else { "…" },
+            (lines - printed).separate_with_commas()
+        )?;
+    }
+    Ok(())
+}
+
+/// The children of the nodes of `pair`, paired by tag: those of the new node
+/// first, in order, then those only found in the old one.
+fn children<'a>(pair: Pair<'a>) -> Vec<Pair<'a>> {
+    let of = |n: Option<&'a Node>| n.and_then(|n| n.children.as_deref()).unwrap_or_default();
+    let (olds, news) = (of(pair.0), of(pair.1));
+    let mut unmatched = olds
+        .iter()
+        .map(|c| (c.tag.as_deref(), c))
+        .collect::<HashMap<_, _>>();
+    let mut children = news
+        .iter()
+        .map(|c| (unmatched.remove(&c.tag.as_deref()), Some(c)))
+        .collect::<Vec<_>>();
+    children.extend(
+        olds.iter()
+            .filter(|c| unmatched.contains_key(&c.tag.as_deref()))
+            .map(|c| (Some(c), None)),
+    );
+    children
+}
+
+fn write_row(
+    (old, new): Pair,
+    path: &str,
+    depth: usize,
+    w_tagline: usize,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let unit = settings.counter;
+    let node = new.or(old).unwrap();
+
+    let len = |n: Option<&Node>| n.map_or(0, |n| n.len);
+    let cardinality = match (len(old), len(new)) {
+        (0, 0) => String::new(),
+        (o, n) if o == n || old.is_none() || new.is_none() => {
+            format!("[{}] ", o.max(n).separate_with_commas())
+        }
+        (o, n) => format!(
+            "[{}{}{}] ",
+            o.separate_with_commas(),
+            if settings.ascii { "->" } else { "→" },
+            n.separate_with_commas()
+        ),
+    };
+    let label = if !settings.show_paths {
+        node.tag.as_deref().unwrap_or_default()
+    } else if path.is_empty() {
+        "/"
+    } else {
+        path
+    };
+    let indent = "  ".repeat(depth - settings.min_depth);
+    let id = format!("{}{}{}", indent, cardinality, label);
+
+    let format = |n: Option<&Node>| {
+        n.map_or_else(
+            || "-".to_owned(),
+            |n| unit.format(n.size(unit), settings.exact),
+        )
+    };
+    let size = |n: Option<&Node>| n.map_or(0, |n| n.size(unit));
+    let (before, after) = (size(old), size(new));
+    let change = match after.cmp(&before) {
+        Ordering::Greater => format!("+{}", unit.format(after - before, settings.exact)),
+        Ordering::Less => format!("-{}", unit.format(before - after, settings.exact)),
+        Ordering::Equal => "0".to_owned(),
+    };
+    let ratio = match (old, new) {
+        (None, _) => "added".to_owned(),
+        (_, None) => "removed".to_owned(),
+        _ if before == after => "0.00%".to_owned(),
+        _ if before == 0 => "-".to_owned(),
+        _ => format!(
+            "{:+.2}%",
+            100. * (after as f64 - before as f64) / before as f64
+        ),
+    };
+    let row = format!(
+        "{} {:>11} {:>11} {:>12} {:>9}",
+        settings.fit(&id, w_tagline),
+        format(old),
+        format(new),
+        change,
+        ratio
+    );
+    let row = match after.cmp(&before) {
+        Ordering::Greater => row.red(),
+        Ordering::Less => row.green(),
+        Ordering::Equal => row.normal(),
+    };
+    writeln!(out, "{}", row)?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::{DisplaySettings, Node, Unit};
+use anyhow::*;
+use std::io::Write;
+
+/// Write the displayed nodes as `du -h` would, children first, to be
+/// processed by `sort -h` & co.
+pub fn render(root: &Node, settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    write_lines(
+        root,
+        String::new(),
+        0,
+        root.size(settings.counter),
+        settings,
+        out,
+    )
+}
+
+/// Format a size with a single-letter suffix, as understood by `sort -h`.
+fn human(x: usize, unit: Unit) -> String {
+    let base = match unit {
+        Unit::Bytes | Unit::Serialized | Unit::Memory | Unit::Keys | Unit::Gzip => 1024.,
+        Unit::Children
+        | Unit::Leaves
+        | Unit::Chars
+        | Unit::Tokens
+        | Unit::Distinct
+        | Unit::Weight => 1000.,
+    };
+    let mut x = x as f64;
+    if x < base {
+        return x.to_string();
+    }
+    for suffix in ["K", "M", "G", "T", "P"] {
+        x /= base;
+        if x < base || suffix == "P" {
+            return if x < 10. {
+                format!("{:.1}{}", (x * 10.).ceil() / 10., suffix)
+            } else {
+                format!("{}{}", x.ceil(), suffix)
+            };
+        }
+    }
+    unreachable!()
+}
+
+fn write_lines(
+    root: &Node,
+    path: String,
+    depth: usize,
+    total_size: usize,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // The nodes still to walk with their path and depth, and whether their
+    // children have been walked and they are to be written; the next one last
+    let mut stack = vec![(root, path, depth, false)];
+    while let Some((node, path, depth, walked)) = stack.pop() {
+        if walked {
+            writeln!(
+                out,
+                "{}\t{}",
+                human(node.size(settings.counter), settings.counter),
+                if path.is_empty() { "/" } else { &path }
+            )?;
+            continue;
+        }
+        if !settings.shows(node, depth, total_size) {
+            continue;
+        }
+        if settings.prints(depth) {
+            stack.push((node, path.clone(), depth, true));
+        }
+        for c in node.children.iter().flatten().rev() {
+            let path = super::child_path(&path, c.tag.as_deref().unwrap_or_default());
+            stack.push((c, path, depth + 1, false));
+        }
+    }
+    Ok(())
+}
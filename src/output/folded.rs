@@ -0,0 +1,47 @@
+use crate::{DisplaySettings, Node};
+use anyhow::*;
+use std::io::Write;
+
+/// Write the displayed nodes as folded stacks, as consumed by
+/// `flamegraph.pl` or inferno. The weight of hidden nodes is attributed to
+/// their parent.
+pub fn render(root: &Node, settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    let total_size = root.size(settings.counter);
+    if !settings.shows(root, 0, total_size) {
+        return Ok(());
+    }
+    // The nodes still to write, with their stack and depth; the next one last
+    let mut stack = vec![(root, frame(root), 0)];
+    while let Some((node, frames, depth)) = stack.pop() {
+        let children = node
+            .children
+            .iter()
+            .flatten()
+            .filter(|c| settings.shows(c, depth + 1, total_size))
+            .collect::<Vec<_>>();
+        // Units such as gzip or distinct are not additive, and the children
+        // of a node may outweigh it
+        let own_size = node.size(settings.counter).saturating_sub(
+            children
+                .iter()
+                .map(|c| c.size(settings.counter))
+                .sum::<usize>(),
+        );
+        if own_size > 0 {
+            writeln!(out, "{} {}", frames, own_size)?;
+        }
+        for child in children.into_iter().rev() {
+            stack.push((child, format!("{};{}", frames, frame(child)), depth + 1));
+        }
+    }
+    Ok(())
+}
+
+/// Semicolons separate frames, and can not be part of a frame name.
+fn frame(node: &Node) -> String {
+    node.tag
+        .as_deref()
+        .unwrap_or_default()
+        .replace(';', "_")
+        .replace('\n', " ")
+}
@@ -0,0 +1,101 @@
+//! Show how the size of a node changed across the snapshots of a document.
+use crate::DisplaySettings;
+use anyhow::*;
+use colored::Colorize;
+use std::cmp::Ordering;
+use std::io::Write;
+
+/// The columns taken by the date, the size, the change and its ratio.
+const W_NUMBERS: usize = 23 + 1 + 11 + 1 + 12 + 1 + 9;
+
+/// Write a row per snapshot, taken at a time in seconds since the epoch, with
+/// the size of the followed node if it is found in the snapshot, and its
+/// change since the previous snapshot.
+pub fn render(
+    sizes: &[(u64, Option<usize>)],
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let unit = settings.counter;
+    let w_bar = settings.width.saturating_sub(W_NUMBERS + 1);
+    if settings.header {
+        let header = format!(
+            "{:<23} {:>11} {:>12} {:>9}",
+            "snapshot",
+            unit.name(),
+            "change",
+            "%"
+        );
+        writeln!(out, "{} {}", header.bold(), "bar".bold())?;
+    }
+
+    let largest = sizes.iter().filter_map(|(_, s)| *s).max().unwrap_or(0);
+    // The size in the previous snapshot, if any
+    let mut previous = None;
+    for &(time, size) in sizes {
+        let (change, ratio, ordering) = match (previous, size) {
+            (Some(Some(before)), Some(after)) => (
+                match after.cmp(&before) {
+                    Ordering::Greater => {
+                        format!("+{}", unit.format(after - before, settings.exact))
+                    }
+                    Ordering::Less => format!("-{}", unit.format(before - after, settings.exact)),
+                    Ordering::Equal => "0".to_owned(),
+                },
+                if before == after {
+                    "0.00%".to_owned()
+                } else if before == 0 {
+                    "-".to_owned()
+                } else {
+                    format!(
+                        "{:+.2}%",
+                        100. * (after as f64 - before as f64) / before as f64
+                    )
+                },
+                after.cmp(&before),
+            ),
+            (Some(None), Some(_)) => (String::new(), "added".to_owned(), Ordering::Greater),
+            (Some(Some(_)), None) => (String::new(), "removed".to_owned(), Ordering::Less),
+            _ => (String::new(), String::new(), Ordering::Equal),
+        };
+        let row = format!(
+            "{:<23} {:>11} {:>12} {:>9}",
+            date(time),
+            size.map_or_else(|| "-".to_owned(), |s| unit.format(s, settings.exact)),
+            change,
+            ratio
+        );
+        let row = match ordering {
+            Ordering::Greater => row.red(),
+            Ordering::Less => row.green(),
+            Ordering::Equal => row.normal(),
+        };
+        let rel = size.unwrap_or(0) as f32 / largest.max(1) as f32;
+        writeln!(out, "{} {}", row, settings.bar(rel, w_bar))?;
+        previous = Some(size);
+    }
+    Ok(())
+}
+
+/// Format `time`, in seconds since the epoch, as a UTC date.
+fn date(time: u64) -> String {
+    // From Howard Hinnant's `civil_from_days`
+    let z = (time / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time % 86400 / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
@@ -0,0 +1,110 @@
+use crate::{DisplaySettings, Node};
+use anyhow::*;
+use colored::Color;
+use std::io::Write;
+use thousands::Separable;
+
+const STYLE: &str = r#"
+body { font-family: monospace; background: #1e1e1e; color: #ddd; }
+details > details, details > .leaf { margin-left: 2em; }
+summary, .leaf { display: flex; align-items: center; gap: 1em; white-space: nowrap; }
+.leaf { margin-left: calc(2em + 1ch); }
+.tag { flex: 0 0 40%; overflow: hidden; text-overflow: ellipsis; }
+.percent { flex: 0 0 8ch; text-align: right; }
+.size { flex: 0 0 12ch; text-align: right; }
+.bar { flex: 1; background: #333; height: 1em; }
+.bar > span { display: block; height: 100%; }
+"#;
+
+/// Only the first levels of the tree are expanded when opening the report.
+const OPEN_DEPTH: usize = 2;
+
+/// Write the displayed nodes as a standalone HTML page.
+pub fn render(root: &Node, settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>toison report</title>
+<style>{}</style>
+</head>
+<body>"#,
+        STYLE
+    )?;
+    write_node(root, 0, root.size(settings.counter), settings, out)?;
+    writeln!(out, "</body>\n</html>")?;
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn css(color: Color) -> String {
+    match color {
+        Color::TrueColor { r, g, b } => format!("rgb({}, {}, {})", r, g, b),
+        _ => "#ddd".to_owned(),
+    }
+}
+
+/// Write `root` and its displayed descendants, walked with an explicit stack
+/// as the tree may be arbitrarily deep.
+fn write_node(
+    root: &Node,
+    depth: usize,
+    total_size: usize,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // The nodes still to write with their depth, or `None` to close the
+    // details of a container; the next one last
+    let mut stack = vec![Some((root, depth))];
+    while let Some(next) = stack.pop() {
+        let Some((node, depth)) = next else {
+            writeln!(out, "</details>")?;
+            continue;
+        };
+        if !settings.shows(node, depth, total_size) {
+            continue;
+        }
+
+        let rel_size = node.size(settings.counter) as f32 / total_size as f32;
+        let cardinality = if node.len > 0 {
+            format!("[{}] ", node.len.separate_with_commas())
+        } else {
+            String::new()
+        };
+        let color = css(settings.color(node, rel_size));
+        let line = format!(
+            r#"<span class="tag" style="color: {color}">{}{}</span><span class="percent">{:.2}%</span><span class="size">({})</span><span class="bar"><span style="width: {:.2}%; background: {color}"></span></span>"#,
+            cardinality,
+            escape(node.tag.as_deref().unwrap_or_default()),
+            100. * rel_size,
+            settings
+                .counter
+                .format(node.size(settings.counter), settings.exact),
+            100. * rel_size,
+            color = color,
+        );
+
+        match &node.children {
+            Some(children) if !children.is_empty() => {
+                writeln!(
+                    out,
+                    "<details{}><summary>{}</summary>",
+                    if depth < OPEN_DEPTH { " open" } else { "" },
+                    line
+                )?;
+                stack.push(None);
+                stack.extend(children.iter().rev().map(|c| Some((c, depth + 1))));
+            }
+            _ => writeln!(out, r#"<div class="leaf">{}</div>"#, line)?,
+        }
+    }
+    Ok(())
+}
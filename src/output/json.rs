@@ -0,0 +1,91 @@
+use crate::{DisplaySettings, Node};
+use anyhow::*;
+use std::io::Write;
+
+/// What is left to write of the report; the report is written as it is
+/// walked rather than built as a `serde_json::Value`, which would be
+/// serialized and dropped recursively.
+enum Step<'a> {
+    /// A displayed node, its path and depth, and whether it is the last
+    /// displayed child of its parent
+    Open(&'a Node, String, usize, bool),
+    /// The end of the children of a node at this depth
+    Close(usize, bool),
+}
+
+/// Write the displayed nodes as a tree of JSON objects.
+pub fn render(root: &Node, settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    let total_size = root.size(settings.counter);
+    let mut stack = vec![Step::Open(root, String::new(), 0, true)];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Open(node, path, depth, last) => {
+                let indent = "    ".repeat(depth);
+                writeln!(out, "{}{{", indent)?;
+                let mut fields = vec![
+                    ("path", serde_json::to_string(&path)?),
+                    ("tag", serde_json::to_string(&node.tag.as_deref())?),
+                    ("depth", depth.to_string()),
+                    ("size_b", node.size_b.to_string()),
+                    ("size_c", node.size_c.to_string()),
+                    ("size_s", node.size_s.to_string()),
+                    ("size_m", node.size_m.to_string()),
+                    ("size_l", node.size_l.to_string()),
+                    ("size_u", node.size_u.to_string()),
+                    ("size_z", node.size_z.to_string()),
+                    ("size_t", node.size_t.to_string()),
+                    ("size_d", node.size_d.to_string()),
+                    ("key_size", node.key_size.to_string()),
+                    (
+                        "percent",
+                        serde_json::to_string(
+                            &(100. * node.size(settings.counter) as f64 / total_size as f64),
+                        )?,
+                    ),
+                ];
+                if node.len > 0 {
+                    fields.push(("len", node.len.to_string()));
+                }
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(out, ",")?;
+                    }
+                    write!(out, "{}  \"{}\": {}", indent, key, value)?;
+                }
+                let Some(children) = &node.children else {
+                    write!(out, "\n{}}}", indent)?;
+                    end(last, out)?;
+                    continue;
+                };
+                write!(out, ",\n{}  \"children\": [", indent)?;
+                let shown = children
+                    .iter()
+                    .filter(|c| settings.shows(c, depth + 1, total_size))
+                    .collect::<Vec<_>>();
+                if shown.is_empty() {
+                    write!(out, "]\n{}}}", indent)?;
+                    end(last, out)?;
+                    continue;
+                }
+                writeln!(out)?;
+                stack.push(Step::Close(depth, last));
+                for (i, child) in shown.iter().enumerate().rev() {
+                    let path = super::child_path(&path, child.tag.as_deref().unwrap_or_default());
+                    stack.push(Step::Open(child, path, depth + 1, i + 1 == shown.len()));
+                }
+            }
+            Step::Close(depth, last) => {
+                let indent = "    ".repeat(depth);
+                write!(out, "{}  ]\n{}}}", indent, indent)?;
+                end(last, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// End an object, followed by a sibling unless it is the `last` one.
+fn end(last: bool, out: &mut dyn Write) -> Result<()> {
+    writeln!(out, "{}", if last { "" } else { "," })?;
+    Ok(())
+}
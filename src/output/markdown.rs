@@ -0,0 +1,77 @@
+use crate::{DisplaySettings, Node};
+use anyhow::*;
+use std::io::Write;
+
+/// Write the displayed nodes as a GitHub-flavored Markdown table.
+pub fn render(root: &Node, settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    let mut header = vec!["Path".to_owned(), "Size".to_owned(), "%".to_owned()];
+    if settings.parent_percent {
+        header.push("% of parent".to_owned());
+    }
+    header.extend(settings.columns.iter().map(|u| u.name()));
+    if settings.show_depth {
+        header.push("Depth".to_owned());
+    }
+    if settings.show_types {
+        header.push("Type".to_owned());
+    }
+    writeln!(out, "| {} |", header.join(" | "))?;
+    writeln!(out, "|:-----|{}", "-----:|".repeat(header.len() - 1))?;
+    write_rows(
+        root,
+        String::new(),
+        0,
+        root.size(settings.counter),
+        root.size(settings.counter),
+        settings,
+        out,
+    )
+}
+
+fn write_rows(
+    root: &Node,
+    path: String,
+    depth: usize,
+    total_size: usize,
+    parent_size: usize,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // The nodes still to write, with their path, depth and parent size; the
+    // next one last
+    let mut stack = vec![(root, path, depth, parent_size)];
+    while let Some((node, path, depth, parent_size)) = stack.pop() {
+        if !settings.shows(node, depth, total_size) {
+            continue;
+        }
+
+        if settings.prints(depth) {
+            let size = node.size(settings.counter);
+            write!(
+                out,
+                "| `{}` | {} | {:.2} |",
+                if path.is_empty() { "/" } else { &path }.replace('|', "\\|"),
+                settings.counter.format(size, settings.exact),
+                100. * size as f32 / total_size as f32,
+            )?;
+            if settings.parent_percent {
+                write!(out, " {:.2} |", 100. * size as f32 / parent_size as f32)?;
+            }
+            for unit in &settings.columns {
+                write!(out, " {} |", unit.format(node.size(*unit), settings.exact))?;
+            }
+            if settings.show_depth {
+                write!(out, " {} |", node.depth)?;
+            }
+            if settings.show_types {
+                write!(out, " `{}` |", node.kind.name())?;
+            }
+            writeln!(out)?;
+        }
+        for c in node.children.iter().flatten().rev() {
+            let path = super::child_path(&path, c.tag.as_deref().unwrap_or_default());
+            stack.push((c, path, depth + 1, node.size(settings.counter)));
+        }
+    }
+    Ok(())
+}
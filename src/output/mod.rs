@@ -0,0 +1,35 @@
+use clap::ValueEnum;
+
+pub mod csv;
+pub mod diff;
+pub mod du;
+pub mod folded;
+pub mod history;
+pub mod html;
+pub mod json;
+pub mod markdown;
+pub mod ncdu;
+pub mod pager;
+pub mod table;
+pub mod treemap;
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+    Tsv,
+    Html,
+    #[value(name = "svg-treemap")]
+    SvgTreemap,
+    Folded,
+    #[value(name = "md")]
+    Markdown,
+    Ncdu,
+    Du,
+}
+
+/// The JSON Pointer of a child of the node at `parent`.
+pub(crate) fn child_path(parent: &str, tag: &str) -> String {
+    format!("{}/{}", parent, tag.replace('~', "~0").replace('/', "~1"))
+}
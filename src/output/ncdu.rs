@@ -0,0 +1,85 @@
+use crate::{DisplaySettings, Node};
+use anyhow::*;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Write the displayed nodes as an ncdu export, to be browsed with
+/// `ncdu -f`. Containers become directories, and the weight of hidden
+/// nodes is attributed to their parent.
+pub fn render(root: &Node, settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    write!(
+        out,
+        r#"[1,0,{{"progname":{},"progver":{},"timestamp":{}}},"#,
+        serde_json::to_string(env!("CARGO_PKG_NAME"))?,
+        serde_json::to_string(env!("CARGO_PKG_VERSION"))?,
+        timestamp
+    )?;
+    let total_size = root.size(settings.counter);
+    if settings.shows(root, 0, total_size) {
+        write_entries(root, total_size, settings, out)?;
+    } else {
+        write!(
+            out,
+            r#"[{{"name":{}}}]"#,
+            serde_json::to_string(&root.tag.as_deref())?
+        )?;
+    }
+    writeln!(out, "]")?;
+    Ok(())
+}
+
+/// Write the entry of `root` and of its displayed descendants, walked with
+/// an explicit stack as the tree may be arbitrarily deep.
+fn write_entries(
+    root: &Node,
+    total_size: usize,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // The nodes still to write with their depth, or `None` to close the
+    // directory of a container; the next one last
+    let mut stack = vec![Some((root, 0, true))];
+    while let Some(next) = stack.pop() {
+        let Some((node, depth, first)) = next else {
+            write!(out, "]")?;
+            continue;
+        };
+        if !first {
+            write!(out, ",")?;
+        }
+        let children = node
+            .children
+            .iter()
+            .flatten()
+            .filter(|c| settings.shows(c, depth + 1, total_size))
+            .collect::<Vec<_>>();
+        // Units such as gzip or distinct are not additive, and the children
+        // of a node may outweigh it
+        let own_size = node.size(settings.counter).saturating_sub(
+            children
+                .iter()
+                .map(|c| c.size(settings.counter))
+                .sum::<usize>(),
+        );
+        let info = format!(
+            r#"{{"name":{},"asize":{},"dsize":{}}}"#,
+            serde_json::to_string(node.tag.as_deref().unwrap_or_default())?,
+            own_size,
+            own_size
+        );
+        if node.children.is_some() {
+            write!(out, "[{}", info)?;
+            stack.push(None);
+            for child in children.into_iter().rev() {
+                stack.push(Some((child, depth + 1, false)));
+            }
+        } else {
+            write!(out, "{}", info)?;
+        }
+    }
+    Ok(())
+}
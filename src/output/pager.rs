@@ -0,0 +1,38 @@
+use anyhow::*;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// Write a rendered report to stdout, through `$PAGER` (or `less`) if it
+/// does not fit in the terminal.
+pub fn show(report: &[u8], height: usize) -> Result<()> {
+    let lines = report.iter().filter(|&&c| c == b'\n').count();
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".to_owned());
+    let mut words = pager.split_whitespace();
+    let command = match words.next() {
+        Some(command) if lines >= height && command != "cat" => command,
+        _ => return write(&mut std::io::stdout(), report),
+    };
+
+    let mut command = Command::new(command);
+    command.args(words).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let mut child = match command.spawn() {
+        Result::Ok(child) => child,
+        // Do not fail on a missing pager
+        Err(_) => return write(&mut std::io::stdout(), report),
+    };
+    write(child.stdin.as_mut().unwrap(), report)?;
+    drop(child.stdin.take());
+    child.wait()?;
+    Ok(())
+}
+
+/// Write the report, ignoring the reader quitting early.
+fn write(out: &mut dyn Write, report: &[u8]) -> Result<()> {
+    match out.write_all(report).and_then(|_| out.flush()) {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        r => Ok(r?),
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{BarStyle, DisplaySettings, Node, BAR_TRACK};
+use anyhow::*;
+use colored::Colorize;
+use std::io::Write;
+use thousands::Separable;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Write the displayed nodes as an indented table, with bars proportional to
+/// their size.
+pub fn render(root: &Node, settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    let at = Position {
+        path: String::new(),
+        depth: 0,
+        indent: String::new(),
+        guides: String::new(),
+    };
+    if settings.header {
+        write_header(settings, out)?;
+    }
+    let mut lines = 0;
+    write_rows(root, at, settings, &mut lines, out)?;
+    let printed = settings.max_lines.map_or(lines, |max| lines.min(max));
+    if lines > printed {
+        writeln!(
+            out,
+            "{} {} more rows",
+            if settings.ascii { "..." } else { "…" },
+            (lines - printed).separate_with_commas()
+        )?;
+    }
+    if settings.summary {
+        write_summary(root, printed, settings, out)?;
+    }
+    Ok(())
+}
+
+/// The widths of the tagline and of the bar of the rows.
+fn layout(settings: &DisplaySettings) -> (usize, usize) {
+    // 11 + 6 + 2 = 19 chars required for numbers, 8 more for the percentage of
+    // the parent, 5 more for the depth, 5 more for the type, 12 more per
+    // additional unit; by default, 2/3 of the rest for the tagline and 1/3 for
+    // the bar
+    let w_numbers = 19
+        + if settings.parent_percent { 8 } else { 0 }
+        + if settings.show_depth { 5 } else { 0 }
+        + if settings.show_types { 5 } else { 0 }
+        + 12 * settings.columns.len();
+    settings.layout(settings.width, w_numbers)
+}
+
+/// Write a line labelling the columns of the rows.
+fn write_header(settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    let (w_tagline, _) = layout(settings);
+    let mut header = format!(
+        "{} {:>7}",
+        settings.fit(if settings.show_paths { "path" } else { "key" }, w_tagline),
+        "%"
+    );
+    if settings.parent_percent {
+        header.push_str(&format!(" {:>7}", "parent"));
+    }
+    for unit in std::iter::once(&settings.counter).chain(settings.columns.iter()) {
+        header.push_str(&format!(" {:>11}", unit.name()));
+    }
+    if settings.show_depth {
+        header.push_str(&format!(" {:>4}", "d"));
+    }
+    if settings.show_types {
+        header.push_str(&format!(" {:>4}", "type"));
+    }
+    writeln!(out, "{} {}", header.bold(), "bar".bold())?;
+    Ok(())
+}
+
+/// Write the totals of the document, and how many nodes were not printed.
+fn write_summary(
+    root: &Node,
+    printed: usize,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    writeln!(out)?;
+    for unit in std::iter::once(&settings.counter).chain(settings.columns.iter()) {
+        writeln!(
+            out,
+            "{:<14}{}",
+            format!("total {}", unit.name()),
+            unit.format(root.size(*unit), settings.exact)
+        )?;
+    }
+    let count = |x: usize| x.separate_with_commas();
+    writeln!(out, "{:<14}{}", "nodes", count(root.size_c + 1))?;
+    writeln!(out, "{:<14}{}", "leaves", count(root.size_l))?;
+    writeln!(out, "{:<14}{}", "max depth", root.depth)?;
+    writeln!(
+        out,
+        "{:<14}{}",
+        "hidden nodes",
+        count(root.count().saturating_sub(printed))
+    )?;
+    Ok(())
+}
+
+/// Where a node lies in the table.
+struct Position {
+    path: String,
+    depth: usize,
+    /// What precedes the tag of the node
+    indent: String,
+    /// What precedes the connectors of the children of the node in tree mode
+    guides: String,
+}
+
+/// Write the rows of the displayed nodes of the tree rooted at `root`,
+/// depth-first, and count them in `lines`.
+fn write_rows(
+    root: &Node,
+    at: Position,
+    settings: &DisplaySettings,
+    lines: &mut usize,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let total_size = root.size(settings.counter);
+    // The nodes still to write, with their position and parent size; the
+    // next one last
+    let mut stack = vec![(root, at, total_size)];
+    while let Some((node, at, parent_size)) = stack.pop() {
+        if !settings.shows(node, at.depth, total_size) {
+            continue;
+        }
+        let printed = settings.prints(at.depth);
+        if printed {
+            // Past the maximal number of lines, rows are only counted
+            if settings.max_lines.is_none_or(|max| *lines < max) {
+                write_row(node, &at, total_size, parent_size, settings, out)?;
+            }
+            *lines += 1;
+        }
+        let Some(children) = &node.children else {
+            continue;
+        };
+        let shown = children
+            .iter()
+            .filter(|c| settings.shows(c, at.depth + 1, total_size))
+            .collect::<Vec<_>>();
+        for (i, child) in shown.iter().enumerate().rev() {
+            let last = i + 1 == shown.len();
+            let (indent, guides) = if !printed {
+                (String::new(), String::new())
+            } else if !settings.tree {
+                (format!("{}  ", at.indent), String::new())
+            } else {
+                let (connector, guide) = match (last, settings.ascii) {
+                    (false, false) => ("├── ", "│   "),
+                    (true, false) => ("└── ", "    "),
+                    (false, true) => ("|-- ", "|   "),
+                    (true, true) => ("`-- ", "    "),
+                };
+                (
+                    format!("{}{}", at.guides, connector),
+                    format!("{}{}", at.guides, guide),
+                )
+            };
+            let at = Position {
+                path: super::child_path(&at.path, child.tag.as_deref().unwrap_or_default()),
+                depth: at.depth + 1,
+                indent,
+                guides,
+            };
+            stack.push((child, at, node.size(settings.counter)));
+        }
+    }
+    Ok(())
+}
+
+fn write_row(
+    node: &Node,
+    at: &Position,
+    total_size: usize,
+    parent_size: usize,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let (w_tagline, w_bar) = layout(settings);
+
+    let rel_size = node.size(settings.counter) as f32 / total_size as f32;
+
+    let cardinality = if node.len > 0 {
+        format!("[{}] ", node.len.to_string().separate_with_commas())
+    } else {
+        String::new()
+    };
+    let label = if !settings.show_paths {
+        node.tag.as_deref().unwrap_or_default()
+    } else if at.path.is_empty() {
+        "/"
+    } else {
+        &at.path
+    };
+    let id = format!("{}{}{}", at.indent, cardinality, label);
+    // When wrapped, continuation lines are aligned on the indentation of the
+    // node, and keep its guides in tree mode
+    let (id, continuations) = if settings.wrap && id.width() > w_tagline {
+        let prefix = if settings.tree {
+            at.guides.clone()
+        } else {
+            " ".repeat(at.indent.width())
+        };
+        let mut lines = wrap(&id, w_tagline, w_tagline.saturating_sub(prefix.width()));
+        let id = lines.remove(0);
+        (
+            id,
+            lines
+                .into_iter()
+                .map(|l| format!("{}{}", prefix, l))
+                .collect(),
+        )
+    } else {
+        (id, Vec::new())
+    };
+
+    let mut header = format!("{} {:>6.2}%", settings.fit(&id, w_tagline), 100. * rel_size);
+    if settings.parent_percent {
+        header.push_str(&format!(
+            " {:>6.2}%",
+            100. * node.size(settings.counter) as f32 / parent_size as f32
+        ));
+    }
+    header.push_str(&format!(
+        " {:>11}",
+        format!(
+            "({})",
+            settings
+                .counter
+                .format(node.size(settings.counter), settings.exact)
+        )
+    ));
+    for unit in &settings.columns {
+        header.push_str(&format!(
+            " {:>11}",
+            format!("({})", unit.format(node.size(*unit), settings.exact))
+        ));
+    }
+    if settings.show_depth {
+        header.push_str(&format!(" {:>4}", format!("d{}", node.depth)));
+    }
+    if settings.show_types {
+        header.push_str(&format!(" {:>4}", node.kind.name()));
+    }
+    let preview = match (settings.preview, &node.preview) {
+        (Some(len), Some(value)) => {
+            let mut preview = value.chars().take(len).collect::<String>();
+            if value.chars().count() > len {
+                preview.push_str(if settings.ascii { "..." } else { "…" });
+            }
+            format!(" \"{}\"", preview.escape_debug())
+        }
+        _ => String::new(),
+    };
+    let color = settings.term_color(node, rel_size);
+    let bar = if settings.bar_style == BarStyle::Background
+        && colored::control::SHOULD_COLORIZE.should_colorize()
+    {
+        let filled = settings.bar_length(rel_size, w_bar);
+        format!(
+            "{}{}",
+            " ".repeat(filled).on_color(color),
+            " ".repeat(w_bar.saturating_sub(filled))
+                .on_color(settings.palette.quantize(BAR_TRACK))
+        )
+    } else {
+        settings.bar(rel_size, w_bar)
+    };
+    writeln!(out, "{} {}{}", header.color(color), bar, preview)?;
+    for line in continuations {
+        writeln!(out, "{}", line.color(color))?;
+    }
+    Ok(())
+}
+
+/// Split `s` on grapheme boundaries in a first line `first` columns wide,
+/// followed by lines `rest` columns wide.
+fn wrap(s: &str, first: usize, rest: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let (mut width, mut max) = (0, first);
+    for g in s.graphemes(true) {
+        if width + g.width() > max && width > 0 {
+            lines.push(String::new());
+            (width, max) = (0, rest);
+        }
+        lines.last_mut().unwrap().push_str(g);
+        width += g.width();
+    }
+    lines
+}
@@ -0,0 +1,203 @@
+use crate::{DisplaySettings, Node};
+use anyhow::*;
+use colored::Color;
+use std::io::Write;
+
+const WIDTH: f64 = 1600.;
+const HEIGHT: f64 = 1000.;
+/// The height of the band holding the label of a node above its children
+const HEADER: f64 = 16.;
+const PADDING: f64 = 2.;
+const CHAR_WIDTH: f64 = 7.5;
+
+#[derive(Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// Write the displayed nodes as an SVG squarified treemap.
+pub fn render(root: &Node, settings: &DisplaySettings, out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" font-size="12">"#,
+        w = WIDTH,
+        h = HEIGHT
+    )?;
+    write_node(
+        root,
+        String::new(),
+        0,
+        root.size(settings.counter),
+        Rect {
+            x: 0.,
+            y: 0.,
+            w: WIDTH,
+            h: HEIGHT,
+        },
+        settings,
+        out,
+    )?;
+    writeln!(out, "</svg>")?;
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn fill(color: Color) -> String {
+    match color {
+        Color::TrueColor { r, g, b } => format!("rgb({},{},{})", r, g, b),
+        _ => "#ccc".to_owned(),
+    }
+}
+
+/// The worst aspect ratio of a row of areas laid along a side of length
+/// `side`.
+fn worst(row: &[f64], side: f64) -> f64 {
+    let sum = row.iter().sum::<f64>();
+    row.iter()
+        .map(|&r| (side * side * r / (sum * sum)).max(sum * sum / (side * side * r)))
+        .fold(0., f64::max)
+}
+
+/// Lay out `areas`, sorted by decreasing size and summing to the area of
+/// `rect`, following the squarified treemap algorithm of Bruls et al.
+fn squarify(areas: &[f64], mut rect: Rect) -> Vec<Rect> {
+    let mut r = Vec::with_capacity(areas.len());
+    let mut start = 0;
+    while start < areas.len() {
+        let side = rect.w.min(rect.h);
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst(&areas[start..=end], side) <= worst(&areas[start..end], side)
+        {
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let sum = row.iter().sum::<f64>();
+        if rect.w >= rect.h {
+            // Stack the row vertically on the left of the remaining space
+            let w = if rect.h > 0. { sum / rect.h } else { 0. };
+            let mut y = rect.y;
+            for a in row {
+                let h = if w > 0. { a / w } else { 0. };
+                r.push(Rect { x: rect.x, y, w, h });
+                y += h;
+            }
+            rect.x += w;
+            rect.w -= w;
+        } else {
+            // Stack the row horizontally on the top of the remaining space
+            let h = if rect.w > 0. { sum / rect.w } else { 0. };
+            let mut x = rect.x;
+            for a in row {
+                let w = if h > 0. { a / h } else { 0. };
+                r.push(Rect { x, y: rect.y, w, h });
+                x += w;
+            }
+            rect.y += h;
+            rect.h -= h;
+        }
+        start = end;
+    }
+    r
+}
+
+/// Write `root` and its displayed descendants, walked with an explicit stack
+/// as the tree may be arbitrarily deep.
+fn write_node(
+    root: &Node,
+    path: String,
+    depth: usize,
+    total_size: usize,
+    rect: Rect,
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // The nodes still to write, with their path, depth and rectangle; the
+    // next one last
+    let mut stack = vec![(root, path, depth, rect)];
+    while let Some((node, path, depth, rect)) = stack.pop() {
+        let size = node.size(settings.counter);
+        let rel_size = size as f32 / total_size as f32;
+        writeln!(
+            out,
+            r##"<g><title>{} ({})</title><rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" stroke="#222"/>"##,
+            if path.is_empty() {
+                escape(node.tag.as_deref().unwrap_or_default())
+            } else {
+                escape(&path)
+            },
+            settings.counter.format(size, settings.exact),
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            fill(settings.color(node, rel_size)),
+        )?;
+        if rect.w > 40. && rect.h > HEADER {
+            // Labels are cut to the approximate number of characters fitting in
+            // the rectangle
+            let fits = ((rect.w - 2. * PADDING) / CHAR_WIDTH) as usize;
+            let label = node.tag.as_deref().unwrap_or_default();
+            let label = if label.chars().count() > fits {
+                format!("{}…", label.chars().take(fits - 1).collect::<String>())
+            } else {
+                label.to_owned()
+            };
+            writeln!(
+                out,
+                r#"<text x="{:.1}" y="{:.1}">{}</text>"#,
+                rect.x + PADDING,
+                rect.y + HEADER - 4.,
+                escape(&label)
+            )?;
+        }
+        writeln!(out, "</g>")?;
+
+        let mut children = node
+            .children
+            .iter()
+            .flatten()
+            .filter(|c| c.size(settings.counter) > 0 && settings.shows(c, depth + 1, total_size))
+            .collect::<Vec<_>>();
+        let inner = Rect {
+            x: rect.x + PADDING,
+            y: rect.y + HEADER,
+            w: rect.w - 2. * PADDING,
+            h: rect.h - HEADER - PADDING,
+        };
+        if children.is_empty() || inner.w <= 0. || inner.h <= 0. || size == 0 {
+            continue;
+        }
+
+        children.sort_by_key(|c| std::cmp::Reverse(c.size(settings.counter)));
+        // Hidden children still take their share of the space
+        let scale = inner.w * inner.h / size as f64;
+        let areas = children
+            .iter()
+            .map(|c| c.size(settings.counter) as f64 * scale)
+            .collect::<Vec<_>>();
+        let mut remaining = inner;
+        let covered = areas.iter().sum::<f64>();
+        if remaining.w >= remaining.h {
+            remaining.w *= covered / (inner.w * inner.h);
+        } else {
+            remaining.h *= covered / (inner.w * inner.h);
+        }
+        let rects = squarify(&areas, remaining);
+        for (child, rect) in children.into_iter().zip(rects).rev() {
+            let path = super::child_path(&path, child.tag.as_deref().unwrap_or_default());
+            stack.push((child, path, depth + 1, rect));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! Keep the analyses of local files over time, to follow their growth.
+use crate::input::ParseSettings;
+use crate::{cache, input, Node};
+use anyhow::*;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The header of snapshot files; unlike that of cache files, it does not
+/// change with every version, but only with the encoding of the nodes, so
+/// that snapshots outlive upgrades.
+const HEADER: &[u8] = b"toison snapshot 1\n";
+
+/// The directory holding the snapshots of `files`; it only depends on their
+/// paths, so that it stays the same across versions.
+fn directory(files: &[String]) -> Result<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".local/share")))
+        .context("no directory to store snapshots in; set XDG_DATA_HOME")?;
+    // FNV-1a, as the hash of the standard library may change between versions
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for file in files {
+        ensure!(
+            file != "-" && !input::is_url(file),
+            "only local files can be snapshotted"
+        );
+        let path = std::path::absolute(file)?;
+        for byte in path.to_string_lossy().bytes().chain([0]) {
+            hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+    Ok(base
+        .join("toison")
+        .join("snapshots")
+        .join(format!("{:016x}", hash)))
+}
+
+/// Store `root`, the analysis of `files`, as their snapshot at the current
+/// time; return where it was stored.
+pub fn take(files: &[String], root: &Node) -> Result<PathBuf> {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    let path = directory(files)?.join(time.to_string());
+    ensure!(
+        !path.exists(),
+        "a snapshot taken at the same time already exists"
+    );
+    cache::write(&path, HEADER, root)?;
+    Ok(path)
+}
+
+/// Load the snapshot stored in `path`, without the nodes pruned by
+/// `settings`.
+pub fn load(path: &Path, settings: &ParseSettings) -> Result<Node> {
+    cache::read(path, HEADER, settings)
+}
+
+/// The snapshots of `files`, oldest first, along with the time they were
+/// taken at, in seconds since the epoch.
+pub fn list(files: &[String]) -> Result<Vec<(u64, PathBuf)>> {
+    let directory = directory(files)?;
+    let entries = match std::fs::read_dir(&directory) {
+        Result::Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        // Snapshots being written have an extension
+        if let Some(time) = path
+            .file_name()
+            .and_then(|n| n.to_str()?.parse::<u128>().ok())
+        {
+            snapshots.push((time, path));
+        }
+    }
+    snapshots.sort();
+    Ok(snapshots
+        .into_iter()
+        .map(|(time, path)| ((time / 1_000_000_000) as u64, path))
+        .collect())
+}
+
+/// The node of `root` at the JSON pointer `pointer`, if any; `/` also
+/// designates the root.
+pub fn find<'a>(root: &'a Node, pointer: &str) -> Result<Option<&'a Node>> {
+    ensure!(
+        pointer.is_empty() || pointer.starts_with('/'),
+        "JSON pointers must be empty or start with `/`"
+    );
+    if pointer == "/" {
+        return Ok(Some(root));
+    }
+    let mut current = root;
+    for token in pointer.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        let Some(child) = current
+            .children
+            .iter()
+            .flatten()
+            .find(|c| c.tag.as_deref() == Some(&token))
+        else {
+            return Ok(None);
+        };
+        current = child;
+    }
+    Ok(Some(current))
+}
@@ -0,0 +1,154 @@
+//! Appearance settings bundled in TOML files, e.g.
+//!
+//! ```toml
+//! colors = "gradient"
+//! gradient = "#2d7dd2:#d7263d"
+//! bar_char = "█"
+//! columns = ["children", "leaves"]
+//! tree = true
+//! ```
+use crate::{Args, BarScale, BarStyle, Colorizer, Unit};
+use anyhow::*;
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use std::path::PathBuf;
+
+/// Find the theme file `theme`, either a path or the name of a theme in the
+/// `toison/themes` configuration directory.
+fn locate(theme: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(theme);
+    if path.is_file() {
+        return Ok(path);
+    }
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| anyhow!("theme `{}` not found", theme))?;
+    let path = config
+        .join("toison")
+        .join("themes")
+        .join(format!("{}.toml", theme));
+    ensure!(
+        path.is_file(),
+        "theme `{}` not found, nor `{}`",
+        theme,
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Apply the settings of the theme `theme` to the appearance flags of `args`
+/// that were not given, as told by their `matches`.
+pub fn apply(theme: &str, args: &mut Args, matches: &ArgMatches) -> Result<()> {
+    let path = locate(theme)?;
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("while reading `{}`", path.display()))?;
+    let table = content
+        .parse::<toml::Table>()
+        .with_context(|| format!("while parsing `{}`", path.display()))?;
+    // Only defaults may be overridden, whatever value they have
+    let unset = |id: &str| {
+        matches!(
+            matches.value_source(id),
+            None | Some(ValueSource::DefaultValue)
+        )
+    };
+    let set = |id: &str, target: &mut bool, value: bool| {
+        if unset(id) {
+            *target = value;
+        }
+    };
+
+    for (key, value) in table {
+        let string = || {
+            value
+                .as_str()
+                .ok_or_else(|| anyhow!("`{}` should be a string", key))
+        };
+        let flag = || {
+            value
+                .as_bool()
+                .ok_or_else(|| anyhow!("`{}` should be a boolean", key))
+        };
+        match key.as_str() {
+            "colors" => {
+                let colors = Colorizer::from_str(string()?, true).map_err(Error::msg)?;
+                if unset("colors") {
+                    args.colors = colors;
+                }
+            }
+            "gradient" => {
+                let gradient = string()?.parse()?;
+                if unset("gradient") {
+                    args.gradient = Some(gradient);
+                }
+            }
+            "heat" => {
+                let heat = string()?.parse()?;
+                if unset("heat") {
+                    args.heat = Some(heat);
+                }
+            }
+            "bar_char" => {
+                let mut chars = string()?.chars();
+                let (Some(c), None) = (chars.next(), chars.next()) else {
+                    bail!("`bar_char` should be a single character");
+                };
+                if unset("bar_char") {
+                    args.bar_char = Some(c);
+                }
+            }
+            "bar_width" => {
+                let width = value
+                    .as_integer()
+                    .and_then(|w| usize::try_from(w).ok())
+                    .ok_or_else(|| anyhow!("`bar_width` should be a positive integer"))?;
+                if unset("bar_width") {
+                    args.bar_width = Some(width);
+                }
+            }
+            "bar_scale" => {
+                let scale = BarScale::from_str(string()?, true).map_err(Error::msg)?;
+                if unset("bar_scale") {
+                    args.bar_scale = scale;
+                }
+            }
+            "bar_style" => {
+                let style = BarStyle::from_str(string()?, true).map_err(Error::msg)?;
+                if unset("bar_style") {
+                    args.bar_style = style;
+                }
+            }
+            "columns" => {
+                let columns = value
+                    .as_array()
+                    .ok_or_else(|| anyhow!("`columns` should be an array of units"))?
+                    .iter()
+                    .map(|u| {
+                        let name = u
+                            .as_str()
+                            .ok_or_else(|| anyhow!("`columns` should be an array of units"))?;
+                        Unit::from_str(name, true).map_err(Error::msg)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                if unset("units") {
+                    args.units.extend(columns);
+                }
+            }
+            "header" => set("no_header", &mut args.no_header, !flag()?),
+            "tree" => set("tree", &mut args.tree, flag()?),
+            "show_depth" => set("show_depth", &mut args.show_depth, flag()?),
+            "show_types" => set("show_types", &mut args.show_types, flag()?),
+            "show_paths" => set("show_paths", &mut args.show_paths, flag()?),
+            "parent_percent" => set("parent_percent", &mut args.parent_percent, flag()?),
+            "exact" => set("exact", &mut args.exact, flag()?),
+            "ascii" => set("ascii", &mut args.ascii, flag()?),
+            _ => bail!("unknown theme setting `{}`", key),
+        }
+    }
+    // The gradient of the theme is of no use to the colors given on the
+    // command line
+    if args.colors != Colorizer::Gradient && unset("gradient") {
+        args.gradient = None;
+    }
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! Count the tokens taken by documents in the context of a language model.
+use clap::ValueEnum;
+use tiktoken_rs::CoreBPE;
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum Tokenizer {
+    /// the encoding of GPT-3.5 & GPT-4
+    Cl100k,
+    /// the encoding of GPT-4o & later models
+    O200k,
+}
+impl Tokenizer {
+    fn bpe(&self) -> &'static CoreBPE {
+        match self {
+            Tokenizer::Cl100k => tiktoken_rs::cl100k_base_singleton(),
+            Tokenizer::O200k => tiktoken_rs::o200k_base_singleton(),
+        }
+    }
+
+    /// The number of tokens in `content`, decoded as UTF-8.
+    pub fn count(&self, content: &[u8]) -> usize {
+        self.bpe()
+            .encode_ordinary(&String::from_utf8_lossy(content))
+            .len()
+    }
+}
@@ -0,0 +1,527 @@
+use crate::{BarStyle, DisplaySettings, Node, Unit, BAR_TRACK};
+use anyhow::*;
+use clap::ValueEnum;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashSet;
+use thousands::Separable;
+
+/// How the children of a node are ordered.
+#[derive(Clone, Copy)]
+enum Sort {
+    Document,
+    Size,
+    Name,
+    Count,
+}
+impl Sort {
+    fn next(self) -> Sort {
+        match self {
+            Sort::Document => Sort::Size,
+            Sort::Size => Sort::Name,
+            Sort::Name => Sort::Count,
+            Sort::Count => Sort::Document,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Sort::Document => "document",
+            Sort::Size => "size",
+            Sort::Name => "name",
+            Sort::Count => "count",
+        }
+    }
+}
+
+/// A line of input being typed in the status bar.
+enum Prompt {
+    /// A search, and where it started from
+    Search(String, Vec<usize>),
+    /// The file where to export the current view
+    Export(String),
+}
+
+/// A node as displayed in the explorer; `path` is the list of child indices
+/// leading to it from the root.
+struct Row<'a> {
+    node: &'a Node,
+    path: Vec<usize>,
+    depth: usize,
+}
+
+struct App<'a> {
+    root: &'a Node,
+    settings: DisplaySettings,
+    /// The units the sizes have been computed in, to cycle through
+    units: Vec<Unit>,
+    expanded: HashSet<Vec<usize>>,
+    /// The visible nodes, in display order, rebuilt whenever they change
+    rows: Vec<Row<'a>>,
+    /// The index of the selected row
+    cursor: usize,
+    page: usize,
+    sort: Sort,
+    prompt: Option<Prompt>,
+    /// The last validated search
+    pattern: String,
+    /// The paths of the nodes matching a pattern, cached until the pattern
+    /// or the displayable nodes change
+    matches: Option<(String, HashSet<Vec<usize>>)>,
+    /// A message to display in the status bar until the next key press
+    message: Option<String>,
+}
+impl<'a> App<'a> {
+    /// The children of `node` along with their index, in display order.
+    fn children(&self, node: &'a Node) -> Vec<(usize, &'a Node)> {
+        let count = |n: &Node| n.len.max(n.children.as_ref().map_or(0, |c| c.len()));
+        let mut children = node
+            .children
+            .iter()
+            .flatten()
+            .enumerate()
+            .collect::<Vec<_>>();
+        match self.sort {
+            Sort::Document => {}
+            Sort::Size => {
+                children.sort_by_key(|(_, c)| std::cmp::Reverse(c.size(self.settings.counter)))
+            }
+            Sort::Name => children.sort_by(|(_, a), (_, b)| a.tag.cmp(&b.tag)),
+            Sort::Count => children.sort_by_key(|(_, c)| std::cmp::Reverse(count(c))),
+        }
+        children
+    }
+
+    /// The currently visible nodes, in display order.
+    fn rows(&self) -> Vec<Row<'a>> {
+        let total_size = self.root.size(self.settings.counter);
+        let mut rows = Vec::new();
+        // The nodes still to walk; the next one last
+        let mut stack = vec![Row {
+            node: self.root,
+            path: Vec::new(),
+            depth: 0,
+        }];
+        while let Some(row) = stack.pop() {
+            if !self.settings.shows(row.node, row.depth, total_size) {
+                continue;
+            }
+            if self.expanded.contains(&row.path) {
+                for (i, child) in self.children(row.node).into_iter().rev() {
+                    let mut path = row.path.clone();
+                    path.push(i);
+                    stack.push(Row {
+                        node: child,
+                        path,
+                        depth: row.depth + 1,
+                    });
+                }
+            }
+            rows.push(row);
+        }
+        rows
+    }
+
+    /// The path of the selected node.
+    fn selected(&self) -> Vec<usize> {
+        self.rows
+            .get(self.cursor)
+            .map(|r| r.path.clone())
+            .unwrap_or_default()
+    }
+
+    /// Rebuild the visible rows, and select the node at `selected`; if it
+    /// has been hidden, its closest visible ancestor.
+    fn refresh(&mut self, selected: &[usize]) {
+        self.rows = self.rows();
+        let mut path = selected;
+        self.cursor = loop {
+            if let Some(i) = self.rows.iter().position(|r| r.path == path) {
+                break i;
+            }
+            if path.is_empty() {
+                break 0;
+            }
+            path = &path[..path.len() - 1];
+        };
+    }
+
+    /// All the displayable nodes in display order, flagged by whether their
+    /// key contains `pattern`, ignoring case; patterns containing a `/` are
+    /// matched against paths.
+    fn search(&self, pattern: &str) -> Vec<(Vec<usize>, bool)> {
+        let pattern = pattern.to_lowercase();
+        let total_size = self.root.size(self.settings.counter);
+        let mut nodes = Vec::new();
+        // The nodes still to search, with their path, pointer and depth; the
+        // next one last
+        let mut stack = vec![(self.root, Vec::new(), String::new(), 0)];
+        while let Some((node, path, pointer, depth)) = stack.pop() {
+            if !self.settings.shows(node, depth, total_size) {
+                continue;
+            }
+            let haystack = if pattern.contains('/') {
+                pointer.as_str()
+            } else {
+                node.tag.as_deref().unwrap_or_default()
+            };
+            let matched = !pattern.is_empty() && haystack.to_lowercase().contains(&pattern);
+            for (i, child) in self.children(node).into_iter().rev() {
+                let mut path = path.clone();
+                path.push(i);
+                let pointer =
+                    crate::output::child_path(&pointer, child.tag.as_deref().unwrap_or_default());
+                stack.push((child, path, pointer, depth + 1));
+            }
+            nodes.push((path, matched));
+        }
+        nodes
+    }
+
+    /// Select the match of `pattern` following (or preceding) `from`,
+    /// expanding it and its ancestors.
+    fn find(&mut self, pattern: &str, from: &[usize], forward: bool, inclusive: bool) {
+        let nodes = self.search(pattern);
+        let n = nodes.len();
+        let start = nodes.iter().position(|(p, _)| p == from).unwrap_or(0);
+        let candidates = if forward {
+            let skip = if inclusive { 0 } else { 1 };
+            (0..n).map(|i| (start + skip + i) % n).collect::<Vec<_>>()
+        } else {
+            (1..=n).map(|i| (start + n - i) % n).collect()
+        };
+        if let Some(found) = candidates.into_iter().find(|&i| nodes[i].1) {
+            let found = &nodes[found].0;
+            for i in 0..=found.len() {
+                self.expanded.insert(found[..i].to_vec());
+            }
+            let found = found.clone();
+            self.refresh(&found);
+        }
+    }
+
+    /// Process a key press while typing in the status bar.
+    fn handle_prompt(&mut self, key: KeyCode) {
+        match (self.prompt.take(), key) {
+            (Some(Prompt::Search(_, origin)), KeyCode::Esc) => self.refresh(&origin),
+            (Some(Prompt::Search(query, _)), KeyCode::Enter) => self.pattern = query,
+            (Some(Prompt::Search(mut query, origin)), key) => {
+                match key {
+                    KeyCode::Backspace => {
+                        query.pop();
+                    }
+                    KeyCode::Char(c) => query.push(c),
+                    _ => {}
+                }
+                self.find(&query, &origin, true, true);
+                self.prompt = Some(Prompt::Search(query, origin));
+            }
+            (Some(Prompt::Export(_)), KeyCode::Esc) => {}
+            (Some(Prompt::Export(path)), KeyCode::Enter) => {
+                self.message = Some(match self.export(&path) {
+                    Result::Ok(()) => format!("exported to `{}`", path),
+                    Err(e) => format!("{:#}", e),
+                });
+            }
+            (Some(Prompt::Export(mut path)), key) => {
+                match key {
+                    KeyCode::Backspace => {
+                        path.pop();
+                    }
+                    KeyCode::Char(c) => path.push(c),
+                    _ => {}
+                }
+                self.prompt = Some(Prompt::Export(path));
+            }
+            (None, _) => {}
+        }
+    }
+
+    /// Write the current view as a plain-text table.
+    fn export(&self, path: &str) -> Result<()> {
+        let total_size = self.root.size(self.settings.counter);
+        let mut out = String::new();
+        for row in &self.rows {
+            let (header, bar) = self.line(row, total_size, self.settings.width);
+            out.push_str(format!("{} {}", header, bar).trim_end());
+            out.push('\n');
+        }
+        std::fs::write(path, out).with_context(|| format!("while writing `{}`", path))
+    }
+
+    /// Format a row as the table report would, as its header and its bar.
+    fn line(&self, row: &Row, total_size: usize, width: usize) -> (String, String) {
+        // Same layout as the table report
+        let (w_tagline, w_bar) = self.settings.layout(width, 19);
+        let rel_size = row.node.size(self.settings.counter) as f32 / total_size as f32;
+        let marker = match (&row.node.children, self.expanded.contains(&row.path)) {
+            (None, _) => "  ",
+            (Some(_), true) if self.settings.ascii => "- ",
+            (Some(_), false) if self.settings.ascii => "+ ",
+            (Some(_), true) => "▾ ",
+            (Some(_), false) => "▸ ",
+        };
+        let cardinality = if row.node.len > 0 {
+            format!("[{}] ", row.node.len.to_string().separate_with_commas())
+        } else {
+            String::new()
+        };
+        let id = format!(
+            "{}{}{}{}",
+            " ".repeat(2 * row.depth),
+            marker,
+            cardinality,
+            row.node.tag.as_deref().unwrap_or_default()
+        );
+        let header = format!(
+            "{} {:>6.2}% {:>11}",
+            self.settings.fit(&id, w_tagline),
+            100. * rel_size,
+            format!(
+                "({})",
+                self.settings
+                    .counter
+                    .format(row.node.size(self.settings.counter), self.settings.exact)
+            ),
+        );
+        let bar = self.settings.bar(rel_size, w_bar);
+        (header, bar)
+    }
+
+    /// Move the cursor to the `i`-th row, or to the closest one.
+    fn select(&mut self, i: isize) {
+        self.cursor = i.clamp(0, self.rows.len().max(1) as isize - 1) as usize;
+    }
+
+    /// Process a key press; return false to quit.
+    fn handle(&mut self, key: KeyCode) -> bool {
+        self.message = None;
+        if self.prompt.is_some() {
+            self.handle_prompt(key);
+            return true;
+        }
+        let cursor = self.cursor as isize;
+        let page = self.page.max(1) as isize;
+        let selected = self.selected();
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.select(cursor + 1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(cursor - 1),
+            KeyCode::PageDown => self.select(cursor + page),
+            KeyCode::PageUp => self.select(cursor - page),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(self.rows.len() as isize),
+            KeyCode::Right | KeyCode::Char('l') => {
+                let expandable = self
+                    .rows
+                    .get(self.cursor)
+                    .is_some_and(|r| r.node.children.is_some());
+                if expandable && self.expanded.insert(selected.clone()) {
+                    self.refresh(&selected);
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                if self.expanded.remove(&selected) {
+                    self.refresh(&selected);
+                } else if let Some(depth) = self.rows.get(self.cursor).map(|r| r.depth) {
+                    // The parent is the closest row above, one level up
+                    if let Some(i) = self.rows[..self.cursor]
+                        .iter()
+                        .rposition(|r| r.depth < depth)
+                    {
+                        self.cursor = i;
+                    }
+                }
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let expandable = self
+                    .rows
+                    .get(self.cursor)
+                    .is_some_and(|r| r.node.children.is_some());
+                if !self.expanded.remove(&selected) && expandable {
+                    self.expanded.insert(selected.clone());
+                }
+                self.refresh(&selected);
+            }
+            KeyCode::Char('u') => {
+                let i = self
+                    .units
+                    .iter()
+                    .position(|u| *u == self.settings.counter)
+                    .unwrap_or(0);
+                self.settings.counter = self.units[(i + 1) % self.units.len()];
+                self.matches = None;
+                self.refresh(&selected);
+            }
+            KeyCode::Char('/') => self.prompt = Some(Prompt::Search(String::new(), selected)),
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.refresh(&selected);
+            }
+            KeyCode::Char('e') => self.prompt = Some(Prompt::Export(String::new())),
+            KeyCode::Char('n') => {
+                let pattern = self.pattern.clone();
+                self.find(&pattern, &selected, true, false);
+            }
+            KeyCode::Char('N') => {
+                let pattern = self.pattern.clone();
+                self.find(&pattern, &selected, false, false);
+            }
+            KeyCode::Char('+') => {
+                self.settings.threshold = (self.settings.threshold + 0.01).min(1.);
+                self.matches = None;
+                self.refresh(&selected);
+            }
+            KeyCode::Char('-') => {
+                self.settings.threshold = (self.settings.threshold - 0.01).max(0.);
+                self.matches = None;
+                self.refresh(&selected);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list_area, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        self.page = list_area.height as usize;
+
+        let total_size = self.root.size(self.settings.counter);
+        let pattern = match &self.prompt {
+            Some(Prompt::Search(query, _)) => query,
+            _ => &self.pattern,
+        };
+        if pattern.is_empty() {
+            self.matches = None;
+        } else if self.matches.as_ref().is_none_or(|(p, _)| p != pattern) {
+            let matches = self
+                .search(pattern)
+                .into_iter()
+                .filter_map(|(path, matched)| matched.then_some(path))
+                .collect();
+            self.matches = Some((pattern.clone(), matches));
+        }
+        let highlighted = |path: &Vec<usize>| {
+            self.matches
+                .as_ref()
+                .is_some_and(|(_, matches)| matches.contains(path))
+        };
+        let items = self
+            .rows
+            .iter()
+            .map(|row| {
+                let rel_size = row.node.size(self.settings.counter) as f32 / total_size as f32;
+                let width = list_area.width as usize;
+                let (header, bar) = self.line(row, total_size, width);
+                let fg = color(self.settings.term_color(row.node, rel_size));
+                let style = Style::default().fg(fg);
+                let bar = if self.settings.bar_style == BarStyle::Background {
+                    let filled = bar.chars().count();
+                    let (_, w_bar) = self.settings.layout(width, 19);
+                    let track = w_bar.saturating_sub(filled);
+                    vec![
+                        Span::styled(" ".repeat(filled), Style::default().bg(fg)),
+                        Span::styled(
+                            " ".repeat(track),
+                            Style::default().bg(color(self.settings.palette.quantize(BAR_TRACK))),
+                        ),
+                    ]
+                } else {
+                    vec![Span::raw(bar)]
+                };
+                let mut spans = vec![
+                    Span::styled(
+                        header,
+                        if highlighted(&row.path) {
+                            style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+                        } else {
+                            style
+                        },
+                    ),
+                    Span::raw(" "),
+                ];
+                spans.extend(bar);
+                ListItem::new(Line::from(spans))
+            })
+            .collect::<Vec<_>>();
+        let mut state = ListState::default().with_selected(Some(self.cursor));
+        frame.render_stateful_widget(
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            list_area,
+            &mut state,
+        );
+
+        let status = match (&self.prompt, &self.message) {
+            (Some(Prompt::Search(query, _)), _) => format!("/{}", query),
+            (Some(Prompt::Export(path)), _) => format!("export to: {}", path),
+            (None, Some(message)) => format!(" {}", message),
+            (None, None) => format!(
+                " unit: {}  threshold: {:.0}%  sort: {}  |  ↑↓ move  ←→ fold  u unit  +/- threshold  s sort  / search  n/N next/prev  e export  q quit",
+                self.settings.counter.name(),
+                100. * self.settings.threshold,
+                self.sort.name(),
+            ),
+        };
+        frame.render_widget(
+            Paragraph::new(status).style(Style::default().add_modifier(Modifier::REVERSED)),
+            status_area,
+        );
+    }
+}
+
+fn color(c: colored::Color) -> Color {
+    match c {
+        colored::Color::TrueColor { r, g, b } => Color::Rgb(r, g, b),
+        colored::Color::AnsiColor(i) => Color::Indexed(i),
+        _ => Color::Reset,
+    }
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Browse the size tree interactively, until the user quits.
+pub fn run(root: &Node, settings: DisplaySettings) -> Result<()> {
+    // The other units are only computed when requested
+    let units = Unit::value_variants()
+        .iter()
+        .copied()
+        .filter(|u| {
+            !matches!(u, Unit::Gzip | Unit::Tokens | Unit::Distinct | Unit::Weight)
+                || *u == settings.counter
+                || settings.columns.contains(u)
+        })
+        .collect();
+    let mut app = App {
+        root,
+        settings,
+        units,
+        expanded: HashSet::from([Vec::new()]),
+        rows: Vec::new(),
+        cursor: 0,
+        page: 0,
+        sort: Sort::Document,
+        prompt: None,
+        pattern: String::new(),
+        matches: None,
+        message: None,
+    };
+    app.refresh(&[]);
+    let mut terminal = ratatui::init();
+    let r = event_loop(&mut terminal, &mut app);
+    ratatui::restore();
+    r
+}